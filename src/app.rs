@@ -1,56 +1,192 @@
-use crate::cli::{Cli, Commands, ToolCommand};
+use crate::cli::{Cli, Commands, ToolCommand, TrashCommand};
 use crate::config::Config;
-use crate::repo::{CommandSpec, Repo};
-use anyhow::Result;
+use crate::error::Error;
+use crate::repo::{ClearFilter, CommandSpec, Repo, ToolLaunch};
+use anyhow::{Context, Result, bail};
+use std::env;
+use std::time::Duration;
 
 pub(crate) fn run(cli: Cli) -> Result<()> {
+    if let Some(dir) = &cli.repo {
+        env::set_current_dir(dir)
+            .with_context(|| format!("cannot change to {}", dir.display()))?;
+    }
+    // `init` doesn't read the config, so a broken one mustn't stop it.
+    let config = match cli.command {
+        Commands::Init => Config::default(),
+        _ => Config::load()?,
+    };
+    let strict = cli.strict;
+    let lock_timeout = cli.lock_timeout.map(Duration::from_secs);
+    let dry_run = cli.dry_run;
+    let discover = || -> Result<Option<Repo>> {
+        Ok(Repo::try_discover(&config)?
+            .map(|repo| repo.with_lock_timeout(lock_timeout).with_dry_run(dry_run)))
+    };
     match cli.command {
         Commands::Create(args) => {
-            let Some(repo) = Repo::try_discover() else { return not_in_repo() };
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
             let command = CommandSpec::from_tail(args.tail);
-            repo.create_worktree(args.name, command)?;
+            repo.create_worktree(args.name, command, args.description, None)?;
         }
         Commands::Switch(args) => {
-            let Some(repo) = Repo::try_discover() else { return not_in_repo() };
-            repo.switch_worktree(args.name, None)?;
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            let command = CommandSpec::from_tail(args.tail);
+            repo.switch_worktree(args.name, command)?;
+        }
+        Commands::Codex(cmd) => run_tool(discover()?, strict, &config, "codex", cmd)?,
+        Commands::Claude(cmd) => run_tool(discover()?, strict, &config, "claude", cmd)?,
+        Commands::List(args) => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.list(args.all)?;
+        }
+        Commands::Resume(args) => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            let (name, launch) = repo.recorded_tool(&args.name)?;
+            let placeholders = repo.placeholders(&name)?;
+            let mut command = CommandSpec {
+                args: config.command_args(
+                    &launch.tool,
+                    launch.profile.as_deref(),
+                    Vec::new(),
+                    &placeholders,
+                )?,
+                program: launch.tool,
+            };
+            command.args.extend(config.resume_args(&command.program));
+            repo.switch_worktree(Some(name), Some(command))?;
+        }
+        Commands::Info(args) => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.info(&args.name)?;
+        }
+        Commands::Recent => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.recent()?;
+        }
+        Commands::Exec(args) => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            let Some(command) = CommandSpec::from_tail(args.command) else {
+                bail!("no command given");
+            };
+            repo.exec(command, args.parallel.get(), args.filter.as_deref())?;
+        }
+        Commands::Mv(args) => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.move_worktree(&args.old, &args.new)?;
+        }
+        Commands::Adopt(args) => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.adopt_worktree(&args.path, args.name)?;
         }
-        Commands::Codex(cmd) => run_tool("codex", cmd)?,
-        Commands::Claude(cmd) => run_tool("claude", cmd)?,
-        Commands::List => {
-            let Some(repo) = Repo::try_discover() else { return not_in_repo() };
-            repo.list()?;
+        Commands::Remove(args) => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.remove_worktree(&args.name, args.force)?;
         }
-        Commands::Clear => {
-            let Some(repo) = Repo::try_discover() else { return not_in_repo() };
-            repo.clear()?;
+        Commands::Archive(args) => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.archive_worktree(&args.name)?;
+        }
+        Commands::Restore(args) => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.restore_worktree(&args.archive, args.name)?;
+        }
+        Commands::Trash(cmd) => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            match cmd {
+                TrashCommand::List => repo.trash_list()?,
+                TrashCommand::Restore(args) => repo.trash_restore(&args.name)?,
+                TrashCommand::Empty => repo.trash_empty()?,
+            }
+        }
+        Commands::Lock(args) => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.lock_worktree(&args.name, args.reason)?;
+        }
+        Commands::Unlock(args) => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.unlock_worktree(&args.name)?;
+        }
+        Commands::Clear(args) => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.clear(&ClearFilter {
+                include_locked: args.include_locked,
+                older_than: args.older_than,
+                merged: args.merged,
+                clean_only: args.clean_only,
+                pattern: args.pattern,
+            })?;
+        }
+        Commands::Gc => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.gc()?;
+        }
+        Commands::Repair => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.repair()?;
+        }
+        Commands::Doctor => {
+            let Some(repo) = discover()? else { return not_in_repo(strict) };
+            repo.doctor()?;
         }
         Commands::Init => Config::init_default()?,
     }
     Ok(())
 }
 
-fn not_in_repo() -> Result<()> {
+fn not_in_repo(strict: bool) -> Result<()> {
+    if strict {
+        bail!(Error::NotInRepo);
+    }
     println!("not in a git repo, doing nothing");
     Ok(())
 }
 
-fn run_tool(name: &str, command: ToolCommand) -> Result<()> {
-    let Some(repo) = Repo::try_discover() else { return not_in_repo() };
-    let config = Config::load().unwrap_or_default();
+fn run_tool(
+    repo: Option<Repo>,
+    strict: bool,
+    config: &Config,
+    name: &str,
+    command: ToolCommand,
+) -> Result<()> {
+    let Some(repo) = repo else { return not_in_repo(strict) };
     match command {
         ToolCommand::Create(args) => {
+            let worktree = repo.new_worktree_name(args.name)?;
+            let placeholders = repo.new_placeholders(&worktree);
             let spec = CommandSpec {
                 program: name.to_string(),
-                args: config.command_args(name, args.extra),
+                args: config.command_args(
+                    name,
+                    args.profile.as_deref(),
+                    args.extra,
+                    &placeholders,
+                )?,
             };
-            repo.create_worktree(args.name, Some(spec))
+            let launch = ToolLaunch {
+                tool: name.to_string(),
+                profile: args.profile,
+            };
+            repo.create_worktree(Some(worktree), Some(spec), args.description, Some(launch))
         }
         ToolCommand::Switch(args) => {
+            let worktree = repo.switch_target(Some(&args.name))?;
+            let placeholders = repo.placeholders(&worktree)?;
             let spec = CommandSpec {
                 program: name.to_string(),
-                args: config.command_args(name, args.extra),
+                args: config.command_args(
+                    name,
+                    args.profile.as_deref(),
+                    args.extra,
+                    &placeholders,
+                )?,
+            };
+            let launch = ToolLaunch {
+                tool: name.to_string(),
+                profile: args.profile,
             };
-            repo.switch_worktree(args.name, Some(spec))
+            repo.record_tool(&worktree, launch)?;
+            repo.switch_worktree(Some(worktree), Some(spec))
         }
     }
 }
@@ -1,8 +1,39 @@
 use clap::{Args, Parser, Subcommand};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(arg_required_else_help = true, about = "Helper for git worktrees")]
 pub(crate) struct Cli {
+    #[arg(
+        short = 'C',
+        long = "repo",
+        global = true,
+        value_name = "DIR",
+        env = "WORKTREE_REPO",
+        help = "Run as if started in DIR instead of the current directory"
+    )]
+    pub(crate) repo: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECONDS",
+        help = "Give up if the repo lock is still held after this many seconds"
+    )]
+    pub(crate) lock_timeout: Option<u64>,
+    #[arg(
+        long,
+        global = true,
+        help = "Treat running outside a git repo as an error (exit code 3)"
+    )]
+    pub(crate) strict: bool,
+    #[arg(
+        long,
+        global = true,
+        help = "Print the git commands and filesystem changes instead of running them"
+    )]
+    pub(crate) dry_run: bool,
     #[command(subcommand)]
     pub(crate) command: Commands,
 }
@@ -18,9 +49,39 @@ pub(crate) enum Commands {
     #[command(subcommand, about = "Run claude inside a worktree")]
     Claude(ToolCommand),
     #[command(about = "List existing worktrees")]
-    List,
+    List(ListArgs),
+    #[command(about = "Relaunch the tool last used in a worktree, continuing its session")]
+    Resume(ResumeArgs),
+    #[command(about = "Show everything known about a worktree")]
+    Info(InfoArgs),
+    #[command(about = "List recently entered worktrees")]
+    Recent,
+    #[command(about = "Run a command in every worktree")]
+    Exec(ExecArgs),
+    #[command(about = "Rename a worktree, and a checked-out branch of the same name")]
+    Mv(MvArgs),
+    #[command(about = "Move a worktree created elsewhere into the worktrees directory")]
+    Adopt(AdoptArgs),
+    #[command(about = "Remove a worktree")]
+    Remove(RemoveArgs),
+    #[command(about = "Snapshot a worktree into refs/worktree-archive/")]
+    Archive(ArchiveArgs),
+    #[command(about = "Recreate a worktree from an archive")]
+    Restore(RestoreArgs),
+    #[command(subcommand, about = "Inspect, restore or empty trashed worktrees")]
+    Trash(TrashCommand),
+    #[command(about = "Lock a worktree so clear skips it")]
+    Lock(LockArgs),
+    #[command(about = "Unlock a locked worktree")]
+    Unlock(UnlockArgs),
     #[command(about = "Clear all .worktrees worktrees")]
-    Clear,
+    Clear(ClearArgs),
+    #[command(about = "Clean up stale worktree metadata and leftover directories")]
+    Gc,
+    #[command(about = "Re-link worktrees after the repository was moved")]
+    Repair,
+    #[command(about = "Check the repo for common worktree problems")]
+    Doctor,
     #[command(about = "Initialize configuration")]
     Init,
 }
@@ -29,14 +90,162 @@ pub(crate) enum Commands {
 pub(crate) struct CreateArgs {
     #[arg(value_name = "NAME")]
     pub(crate) name: Option<String>,
+    #[arg(
+        long,
+        value_name = "TEXT",
+        help = "Note what the worktree is for; shown by list"
+    )]
+    pub(crate) description: Option<String>,
     #[arg(value_name = "COMMAND", trailing_var_arg = true)]
     pub(crate) tail: Vec<String>,
 }
 
 #[derive(Args)]
-pub(crate) struct SwitchArgs {
+pub(crate) struct ExecArgs {
+    #[arg(long, value_name = "N", default_value = "1")]
+    pub(crate) parallel: NonZeroUsize,
+    #[arg(long, value_name = "GLOB")]
+    pub(crate) filter: Option<String>,
+    #[arg(value_name = "COMMAND", required = true, trailing_var_arg = true)]
+    pub(crate) command: Vec<String>,
+}
+
+#[derive(Args)]
+pub(crate) struct RemoveArgs {
     #[arg(value_name = "NAME")]
     pub(crate) name: String,
+    #[arg(long, help = "Remove even with local changes or a lock")]
+    pub(crate) force: bool,
+}
+
+#[derive(Args)]
+pub(crate) struct ArchiveArgs {
+    #[arg(value_name = "NAME")]
+    pub(crate) name: String,
+}
+
+#[derive(Args)]
+pub(crate) struct RestoreArgs {
+    #[arg(
+        value_name = "ARCHIVE",
+        help = "Archive ref, <name>/<timestamp>, or <name> for the latest"
+    )]
+    pub(crate) archive: String,
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Restore under a different worktree name"
+    )]
+    pub(crate) name: Option<String>,
+}
+
+#[derive(Subcommand)]
+#[command(subcommand_required = true, arg_required_else_help = true)]
+pub(crate) enum TrashCommand {
+    #[command(about = "List trashed worktrees")]
+    List,
+    #[command(about = "Move a trashed worktree back")]
+    Restore(TrashRestoreArgs),
+    #[command(about = "Delete every trashed worktree")]
+    Empty,
+}
+
+#[derive(Args)]
+pub(crate) struct TrashRestoreArgs {
+    #[arg(value_name = "NAME")]
+    pub(crate) name: String,
+}
+
+#[derive(Args)]
+pub(crate) struct ListArgs {
+    #[arg(
+        long,
+        help = "Also show worktrees created outside the worktrees directory"
+    )]
+    pub(crate) all: bool,
+}
+
+#[derive(Args)]
+pub(crate) struct ResumeArgs {
+    #[arg(value_name = "NAME")]
+    pub(crate) name: String,
+}
+
+#[derive(Args)]
+pub(crate) struct InfoArgs {
+    #[arg(value_name = "NAME")]
+    pub(crate) name: String,
+}
+
+#[derive(Args)]
+pub(crate) struct AdoptArgs {
+    #[arg(value_name = "PATH")]
+    pub(crate) path: PathBuf,
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Name to give it (default: the directory name)"
+    )]
+    pub(crate) name: Option<String>,
+}
+
+#[derive(Args)]
+pub(crate) struct MvArgs {
+    #[arg(value_name = "OLD")]
+    pub(crate) old: String,
+    #[arg(value_name = "NEW")]
+    pub(crate) new: String,
+}
+
+#[derive(Args)]
+pub(crate) struct LockArgs {
+    #[arg(value_name = "NAME")]
+    pub(crate) name: String,
+    #[arg(long, value_name = "REASON")]
+    pub(crate) reason: Option<String>,
+}
+
+#[derive(Args)]
+pub(crate) struct UnlockArgs {
+    #[arg(value_name = "NAME")]
+    pub(crate) name: String,
+}
+
+#[derive(Args)]
+pub(crate) struct ClearArgs {
+    #[arg(long, help = "Also remove worktrees locked with `worktree lock`")]
+    pub(crate) include_locked: bool,
+    #[arg(
+        long,
+        value_name = "AGE",
+        value_parser = parse_age,
+        help = "Only remove worktrees created more than AGE ago (e.g. 7d, 12h, 30m)"
+    )]
+    pub(crate) older_than: Option<Duration>,
+    #[arg(
+        long,
+        value_name = "BRANCH",
+        num_args = 0..=1,
+        default_missing_value = "HEAD",
+        help = "Only remove worktrees whose HEAD is reachable from BRANCH (default: the main checkout's HEAD)"
+    )]
+    pub(crate) merged: Option<String>,
+    #[arg(long, help = "Only remove worktrees without local changes")]
+    pub(crate) clean_only: bool,
+    #[arg(
+        long = "match",
+        value_name = "GLOB",
+        help = "Only remove worktrees whose name matches GLOB"
+    )]
+    pub(crate) pattern: Option<String>,
+}
+
+#[derive(Args)]
+pub(crate) struct SwitchArgs {
+    #[arg(value_name = "NAME")]
+    pub(crate) name: Option<String>,
+    #[arg(value_name = "COMMAND", trailing_var_arg = true)]
+    pub(crate) tail: Vec<String>,
 }
 
 #[derive(Subcommand)]
@@ -48,16 +257,56 @@ pub(crate) enum ToolCommand {
 
 #[derive(Args)]
 pub(crate) struct ToolCreateArgs {
+    #[arg(
+        long,
+        value_name = "PROFILE",
+        help = "Use a named args profile from the config"
+    )]
+    pub(crate) profile: Option<String>,
     #[arg(value_name = "NAME")]
     pub(crate) name: Option<String>,
+    #[arg(
+        long,
+        value_name = "TEXT",
+        help = "Note what the worktree is for; shown by list"
+    )]
+    pub(crate) description: Option<String>,
     #[arg(value_name = "ARGS", trailing_var_arg = true)]
     pub(crate) extra: Vec<String>,
 }
 
 #[derive(Args)]
 pub(crate) struct ToolSwitchArgs {
+    #[arg(
+        long,
+        value_name = "PROFILE",
+        help = "Use a named args profile from the config"
+    )]
+    pub(crate) profile: Option<String>,
     #[arg(value_name = "NAME")]
     pub(crate) name: String,
     #[arg(value_name = "ARGS", trailing_var_arg = true)]
     pub(crate) extra: Vec<String>,
 }
+
+fn parse_age(value: &str) -> Result<Duration, String> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("expected an age like 7d or 12h, got '{}'", value))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" | "" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown age unit '{}'; use s, m, h, d or w", unit)),
+    };
+    amount
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("age '{}' is too large", value))
+}
@@ -1,12 +1,24 @@
-use anyhow::Result;
+use crate::error::Error;
+use anyhow::{Result, bail};
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_TRASH_RETENTION_DAYS: u64 = 7;
 
 #[derive(Default, Deserialize)]
 pub(crate) struct Config {
+    #[serde(default)]
+    worktrees_dir: Option<String>,
+    #[serde(default)]
+    archive_on_remove: bool,
+    #[serde(default)]
+    trash: bool,
+    #[serde(default)]
+    trash_retention_days: Option<u64>,
     #[serde(default)]
     commands: HashMap<String, CommandConfig>,
 }
@@ -17,13 +29,98 @@ struct CommandConfig {
     args: Vec<String>,
     #[serde(default)]
     replace_defaults: bool,
+    #[serde(default)]
+    resume_args: Option<Vec<String>>,
+    #[serde(default)]
+    profiles: HashMap<String, ProfileConfig>,
+}
+
+/// Values for the `{placeholder}`s allowed in configured tool args. There are
+/// no hooks yet; when they arrive, their commands should expand these too.
+pub(crate) struct Placeholders {
+    pub(crate) name: String,
+    pub(crate) path: String,
+    pub(crate) root: String,
+    pub(crate) repo: String,
+    pub(crate) branch: String,
+    pub(crate) base: String,
+}
+
+impl Placeholders {
+    const KEYS: [&str; 6] = ["name", "path", "root", "repo", "branch", "base"];
+
+    fn get(&self, key: &str) -> Option<&str> {
+        match key {
+            "name" => Some(&self.name),
+            "path" => Some(&self.path),
+            "root" => Some(&self.root),
+            "repo" => Some(&self.repo),
+            "branch" => Some(&self.branch),
+            "base" => Some(&self.base),
+            _ => None,
+        }
+    }
+
+    /// Replaces each `{key}` in `arg`; `{{` and `}}` stand for literal braces.
+    fn expand(&self, arg: &str) -> Result<String, String> {
+        let mut expanded = String::with_capacity(arg.len());
+        let mut rest = arg;
+        while let Some(start) = rest.find(['{', '}']) {
+            expanded.push_str(&rest[..start]);
+            let tail = &rest[start..];
+            if tail.starts_with("{{") || tail.starts_with("}}") {
+                expanded.push_str(&tail[..1]);
+                rest = &tail[2..];
+            } else if let Some(after) = tail.strip_prefix('}') {
+                expanded.push('}');
+                rest = after;
+            } else {
+                let Some(end) = tail.find('}') else {
+                    return Err(format!("unclosed placeholder in '{}'", arg));
+                };
+                let key = &tail[1..end];
+                let Some(value) = self.get(key) else {
+                    return Err(format!(
+                        "unknown placeholder '{{{}}}' in '{}'; known: {}",
+                        key,
+                        arg,
+                        Self::KEYS.map(|key| format!("{{{}}}", key)).join(", ")
+                    ));
+                };
+                expanded.push_str(value);
+                rest = &tail[end + 1..];
+            }
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+}
+
+/// A named set of args for a tool, picked with `--profile`.
+#[derive(Default, Deserialize)]
+struct ProfileConfig {
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    replace_defaults: bool,
 }
 
 impl Config {
+    /// Loads `~/.worktree/config.toml`, falling back to defaults when it doesn't exist.
     pub(crate) fn load() -> Result<Self> {
         let path = config_path()?;
-        let contents = fs::read_to_string(path)?;
-        Ok(toml::from_str(&contents)?)
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => bail!(Error::Config(format!(
+                "failed to read {}: {}",
+                path.display(),
+                err
+            ))),
+        };
+        toml::from_str(&contents).map_err(|err| {
+            Error::Config(format!("invalid config {}: {}", path.display(), err)).into()
+        })
     }
 
     pub(crate) fn init_default() -> Result<()> {
@@ -38,18 +135,129 @@ impl Config {
         Ok(())
     }
 
-    pub(crate) fn command_args(&self, name: &str, extra: Vec<String>) -> Vec<String> {
-        let mut args = Vec::new();
+    /// Where worktrees for the repo `repo` at `root` live. `~` expands to the
+    /// home directory, `{repo}` to the repo name, a trailing `/{name}` is
+    /// allowed for readability, and relative paths are taken relative to the
+    /// repo root.
+    pub(crate) fn worktrees_dir(&self, root: &Path, repo: &str) -> Result<PathBuf> {
+        let Some(template) = &self.worktrees_dir else {
+            return Ok(root.join(".worktrees"));
+        };
+        // `.../{name}` describes each worktree's path; the directory is its parent.
+        let template = template.strip_suffix("/{name}").unwrap_or(template);
+        if template.contains("{name}") {
+            bail!(Error::Config(format!(
+                "worktrees_dir '{}': {{name}} is only allowed as the last path component",
+                self.worktrees_dir.as_deref().unwrap_or_default()
+            )));
+        }
+        let expanded = template.replace("{repo}", repo);
+        if expanded.contains('{') {
+            bail!(Error::Config(format!(
+                "worktrees_dir '{}': unknown placeholder; use {{repo}} or a trailing /{{name}}",
+                template
+            )));
+        }
+        let path = match expanded.strip_prefix("~/") {
+            Some(rest) => home_dir()?.join(rest),
+            None if expanded == "~" => home_dir()?,
+            None => PathBuf::from(expanded),
+        };
+        Ok(if path.is_absolute() {
+            path
+        } else {
+            root.join(path)
+        })
+    }
+
+    pub(crate) fn archive_on_remove(&self) -> bool {
+        self.archive_on_remove
+    }
+
+    /// Days to keep trashed worktrees, or `None` when the trash is disabled.
+    pub(crate) fn trash_retention_days(&self) -> Option<u64> {
+        self.trash.then(|| {
+            self.trash_retention_days
+                .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS)
+        })
+    }
+
+    /// Built-in defaults, then the tool's configured args, then the profile's
+    /// args, then `extra`. A profile with `replace_defaults` drops everything
+    /// before its own args. Placeholders in configured args are expanded from
+    /// `placeholders`; `extra` is passed through as typed.
+    pub(crate) fn command_args(
+        &self,
+        name: &str,
+        profile: Option<&str>,
+        extra: Vec<String>,
+        placeholders: &Placeholders,
+    ) -> Result<Vec<String>> {
         let command = self.commands.get(name);
-        let replace_defaults = command.is_some_and(|cmd| cmd.replace_defaults);
-        if !replace_defaults {
-            args.extend(builtin_command_args(name));
+        let profile = match profile {
+            Some(profile) => Some(
+                command
+                    .and_then(|cmd| cmd.profiles.get(profile))
+                    .ok_or_else(|| unknown_profile(name, profile, command))?,
+            ),
+            None => None,
+        };
+        let mut args = Vec::new();
+        if !profile.is_some_and(|profile| profile.replace_defaults) {
+            let replace_defaults = command.is_some_and(|cmd| cmd.replace_defaults);
+            if !replace_defaults {
+                args.extend(builtin_command_args(name));
+            }
+            if let Some(command) = command {
+                args.extend(command.args.iter().cloned());
+            }
         }
-        if let Some(command) = command {
-            args.extend(command.args.iter().cloned());
+        if let Some(profile) = profile {
+            args.extend(profile.args.iter().cloned());
         }
+        let mut args = args
+            .iter()
+            .map(|arg| placeholders.expand(arg))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| Error::Config(format!("{} args: {}", name, err)))?;
         args.extend(extra);
-        args
+        Ok(args)
+    }
+
+    /// Args appended by `worktree resume` to pick up the tool's last session.
+    /// A configured `resume_args` replaces the built-in ones.
+    pub(crate) fn resume_args(&self, name: &str) -> Vec<String> {
+        self.commands
+            .get(name)
+            .and_then(|command| command.resume_args.clone())
+            .unwrap_or_else(|| builtin_resume_args(name))
+    }
+}
+
+fn unknown_profile(name: &str, profile: &str, command: Option<&CommandConfig>) -> Error {
+    let mut available: Vec<_> = command
+        .map(|cmd| cmd.profiles.keys().map(String::as_str).collect())
+        .unwrap_or_default();
+    available.sort_unstable();
+    if available.is_empty() {
+        return Error::Config(format!(
+            "unknown profile '{}': no profiles are configured for {}",
+            profile, name
+        ));
+    }
+    Error::Config(format!(
+        "unknown profile '{}' for {}; available: {}",
+        profile,
+        name,
+        available.join(", ")
+    ))
+}
+
+fn builtin_resume_args(name: &str) -> Vec<String> {
+    match name {
+        "codex" => vec!["resume".into(), "--last".into()],
+        "claude" => vec!["--continue".into()],
+        _ => vec![],
     }
 }
 
@@ -73,20 +281,22 @@ fn home_dir() -> Result<PathBuf> {
     if let Ok(home) = env::var("USERPROFILE") {
         return Ok(PathBuf::from(home));
     }
-    anyhow::bail!("failed to determine home directory")
+    bail!(Error::Config(
+        "failed to determine home directory".to_string()
+    ))
 }
 
-fn display_path(path: &PathBuf) -> PathBuf {
-    if let Ok(home) = home_dir() {
-        if path.starts_with(&home) {
-            let mut buf = PathBuf::from("~");
-            if let Ok(stripped) = path.strip_prefix(&home) {
-                buf.push(stripped);
-            }
-            return buf;
+fn display_path(path: &Path) -> PathBuf {
+    if let Ok(home) = home_dir()
+        && path.starts_with(&home)
+    {
+        let mut buf = PathBuf::from("~");
+        if let Ok(stripped) = path.strip_prefix(&home) {
+            buf.push(stripped);
         }
+        return buf;
     }
-    path.clone()
+    path.to_path_buf()
 }
 
 fn default_config_contents() -> &'static str {
@@ -95,14 +305,41 @@ fn default_config_contents() -> &'static str {
 # If a tool has baked-in defaults, your args are appended by default. To replace
 # the baked-in defaults entirely, set `replace_defaults = true`.
 
+# Where worktrees are created. Defaults to `.worktrees` inside the repo root.
+# `~` expands to your home directory and `{repo}` to the repo directory name.
+# worktrees_dir = "~/.worktrees/{repo}"
+
+# Snapshot each worktree into refs/worktree-archive/ before `remove` or `clear`
+# deletes it, so `worktree restore` can bring it back.
+# archive_on_remove = true
+
+# Move worktrees into a trash area on `remove` and `clear` instead of deleting
+# them; `worktree trash restore <name>` undoes it. Trashed worktrees are deleted
+# for good after `trash_retention_days`.
+# trash = true
+# trash_retention_days = 7
+
 [commands.codex]
 # Built-in defaults:
 #   ["--dangerously-bypass-approvals-and-sandbox"]
 args = []
+# Appended by `worktree resume`; built-in default: ["resume", "--last"]
+# resume_args = ["resume", "--last"]
 
 [commands.claude]
 # Built-in defaults:
 #   ["--dangerously-skip-permissions"]
 args = []
+# Appended by `worktree resume`; built-in default: ["--continue"]
+# resume_args = ["--continue"]
+
+# Args may use {name}, {path}, {root}, {repo}, {branch} and {base}, e.g.
+# args = ["--add-dir", "{root}"]; write {{ and }} for literal braces.
+
+# Named profiles, picked with `worktree claude create --profile review`. Their
+# args go after the ones above; `replace_defaults = true` uses only the profile's.
+# [commands.claude.profiles.review]
+# args = ["--permission-mode", "plan"]
+# replace_defaults = true
 "#
 }
@@ -0,0 +1,77 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Failures that scripts may want to tell apart. Each maps to a distinct exit
+/// code (see the README); anything else exits with 1.
+#[derive(Debug)]
+pub(crate) enum Error {
+    NotInRepo,
+    InvalidName(String),
+    WorktreeExists(String),
+    NotADirectory(PathBuf),
+    WorktreeMissing(String),
+    AmbiguousName {
+        query: String,
+        candidates: Vec<String>,
+    },
+    LockTimeout(String),
+    Git(String),
+    Config(String),
+    ExecFailed {
+        failed: usize,
+        total: usize,
+    },
+}
+
+impl Error {
+    pub(crate) fn exit_code(&self) -> u8 {
+        match self {
+            Self::NotInRepo => 3,
+            Self::InvalidName(_) => 4,
+            Self::WorktreeExists(_) | Self::NotADirectory(_) => 5,
+            Self::WorktreeMissing(_) => 6,
+            Self::AmbiguousName { .. } => 7,
+            Self::LockTimeout(_) => 8,
+            Self::Git(_) => 9,
+            Self::Config(_) => 10,
+            Self::ExecFailed { .. } => 11,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotInRepo => write!(f, "not in a git repo"),
+            Self::InvalidName(name) => write!(f, "invalid worktree name '{}'", name),
+            Self::WorktreeExists(name) => write!(f, "worktree '{}' already exists", name),
+            Self::NotADirectory(path) => write!(
+                f,
+                "worktree path exists and is not a directory: {}",
+                path.display()
+            ),
+            Self::WorktreeMissing(name) => write!(f, "worktree '{}' does not exist", name),
+            Self::AmbiguousName { query, candidates } => write!(
+                f,
+                "worktree name '{}' is ambiguous; candidates: {}",
+                query,
+                candidates.join(", ")
+            ),
+            Self::LockTimeout(message) | Self::Git(message) | Self::Config(message) => {
+                write!(f, "{}", message)
+            }
+            Self::ExecFailed { failed, total } => {
+                write!(f, "{} of {} worktrees failed", failed, total)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The exit code for a top-level error, looking through any added context.
+pub(crate) fn exit_code(err: &anyhow::Error) -> u8 {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<Error>())
+        .map_or(1, Error::exit_code)
+}
@@ -1,7 +1,17 @@
+use crate::error::Error;
 use anyhow::{Context, Result, bail};
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use std::process;
 
+pub(crate) struct WorktreeEntry {
+    pub(crate) path: PathBuf,
+    pub(crate) bare: bool,
+    pub(crate) locked: Option<String>,
+    /// Set when git considers the entry stale, e.g. its directory is gone.
+    pub(crate) prunable: Option<String>,
+}
+
 pub(crate) fn stdout<const N: usize>(args: [&str; N]) -> Result<String> {
     let output = process::Command::new("git")
         .args(args)
@@ -13,13 +23,57 @@ pub(crate) fn stdout<const N: usize>(args: [&str; N]) -> Result<String> {
         let stderr = String::from_utf8_lossy(&output.stderr);
         let stderr = stderr.trim();
         if stderr.is_empty() {
-            bail!("git {:?} failed", args);
+            bail!(Error::Git(format!("git {:?} failed", args)));
         }
-        bail!("git {:?} failed: {}", args, stderr);
+        bail!(Error::Git(format!("git {:?} failed: {}", args, stderr)));
     }
 }
 
-pub(crate) fn worktree_paths(root: &Path) -> Result<Vec<PathBuf>> {
+pub(crate) fn run_in<I, S>(dir: &Path, args: I) -> Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    run_in_env(dir, &[], args)
+}
+
+pub(crate) fn run_in_env<I, S>(dir: &Path, envs: &[(&str, &OsStr)], args: I) -> Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let args: Vec<_> = args
+        .into_iter()
+        .map(|arg| arg.as_ref().to_os_string())
+        .collect();
+    let output = process::Command::new("git")
+        .args(&args)
+        .envs(envs.iter().copied())
+        .current_dir(dir)
+        .output()
+        .context("failed to run git")?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr = stderr.trim();
+        if stderr.is_empty() {
+            bail!(Error::Git(format!("git {:?} failed", args)));
+        }
+        bail!(Error::Git(format!("git {:?} failed: {}", args, stderr)));
+    }
+}
+
+/// Whether `git worktree add` knows `--orphan` (git 2.42+), judged from its
+/// usage text since `-h` exits non-zero.
+pub(crate) fn supports_orphan_worktrees() -> bool {
+    process::Command::new("git")
+        .args(["worktree", "add", "-h"])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("--orphan"))
+}
+
+pub(crate) fn worktree_list(root: &Path) -> Result<Vec<WorktreeEntry>> {
     let output = process::Command::new("git")
         .arg("worktree")
         .arg("list")
@@ -29,27 +83,49 @@ pub(crate) fn worktree_paths(root: &Path) -> Result<Vec<PathBuf>> {
         .context("failed to run git worktree list")?;
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("git worktree list failed: {}", stderr.trim());
+        bail!(Error::Git(format!(
+            "git worktree list failed: {}",
+            stderr.trim()
+        )));
     }
     parse_worktree_list(&String::from_utf8_lossy(&output.stdout), root)
 }
 
-fn parse_worktree_list(output: &str, root: &Path) -> Result<Vec<PathBuf>> {
-    let mut paths = Vec::new();
+fn parse_worktree_list(output: &str, root: &Path) -> Result<Vec<WorktreeEntry>> {
+    let mut entries: Vec<WorktreeEntry> = Vec::new();
     for line in output.lines() {
-        let Some(rest) = line.strip_prefix("worktree ") else {
+        if let Some(rest) = line.strip_prefix("worktree ") {
+            let raw = rest.trim();
+            if raw.is_empty() {
+                continue;
+            }
+            let path = PathBuf::from(raw);
+            entries.push(WorktreeEntry {
+                path: if path.is_absolute() {
+                    path
+                } else {
+                    root.join(path)
+                },
+                bare: false,
+                locked: None,
+                prunable: None,
+            });
             continue;
-        };
-        let raw = rest.trim();
-        if raw.is_empty() {
+        }
+        let Some(entry) = entries.last_mut() else {
             continue;
+        };
+        if line == "bare" {
+            entry.bare = true;
+        } else if line == "locked" {
+            entry.locked = Some(String::new());
+        } else if let Some(reason) = line.strip_prefix("locked ") {
+            entry.locked = Some(reason.trim().to_string());
+        } else if line == "prunable" {
+            entry.prunable = Some(String::new());
+        } else if let Some(reason) = line.strip_prefix("prunable ") {
+            entry.prunable = Some(reason.trim().to_string());
         }
-        let path = PathBuf::from(raw);
-        paths.push(if path.is_absolute() {
-            path
-        } else {
-            root.join(path)
-        });
     }
-    Ok(paths)
+    Ok(entries)
 }
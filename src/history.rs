@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_ENTRIES: usize = 50;
+
+pub(crate) struct History {
+    path: PathBuf,
+}
+
+pub(crate) struct HistoryEntry {
+    pub(crate) name: String,
+    pub(crate) entered_at: u64,
+}
+
+impl History {
+    pub(crate) fn new(git_common_dir: &Path) -> Self {
+        Self {
+            path: git_common_dir.join("worktree-tool.history"),
+        }
+    }
+
+    /// Entries ordered from most to least recently entered.
+    pub(crate) fn entries(&self) -> Result<Vec<HistoryEntry>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+        let contents = fs::read_to_string(&self.path)
+            .with_context(|| format!("failed to read {}", self.path.display()))?;
+        let mut entries: Vec<_> = contents
+            .lines()
+            .filter_map(|line| {
+                let (entered_at, name) = line.split_once('\t')?;
+                Some(HistoryEntry {
+                    name: name.to_string(),
+                    entered_at: entered_at.parse().ok()?,
+                })
+            })
+            .collect();
+        entries.reverse();
+        Ok(entries)
+    }
+
+    pub(crate) fn record(&self, name: &str) -> Result<()> {
+        let mut entries = self.entries()?;
+        entries.retain(|entry| entry.name != name);
+        entries.insert(
+            0,
+            HistoryEntry {
+                name: name.to_string(),
+                entered_at: now(),
+            },
+        );
+        entries.truncate(MAX_ENTRIES);
+        self.write(&entries)
+    }
+
+    /// Carries the history of a renamed worktree over to its new name.
+    pub(crate) fn rename(&self, old: &str, new: &str) -> Result<()> {
+        let mut entries = self.entries()?;
+        if !entries.iter().any(|entry| entry.name == old) {
+            return Ok(());
+        }
+        entries.retain(|entry| entry.name != new);
+        for entry in &mut entries {
+            if entry.name == old {
+                entry.name = new.to_string();
+            }
+        }
+        self.write(&entries)
+    }
+
+    fn write(&self, entries: &[HistoryEntry]) -> Result<()> {
+        let contents: String = entries
+            .iter()
+            .rev()
+            .map(|entry| format!("{}\t{}\n", entry.entered_at, entry.name))
+            .collect();
+        let tmp = self
+            .path
+            .with_extension(format!("history.{}.tmp", process::id()));
+        fs::write(&tmp, contents).with_context(|| format!("failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("failed to write {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+pub(crate) fn format_age(timestamp: u64) -> String {
+    let secs = now().saturating_sub(timestamp);
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
@@ -1,26 +1,154 @@
-use anyhow::{Context, Result};
+use crate::error::Error;
+use crate::history;
+use anyhow::{Context, Result, bail};
 use fs2::FileExt;
-use std::fs::{File, OpenOptions};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
 use std::path::Path;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const NOTICE_AFTER: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 pub(crate) struct RepoLock {
-    _file: File,
+    file: File,
 }
 
 impl RepoLock {
-    pub(crate) fn acquire(path: &Path) -> Result<Self> {
+    pub(crate) fn acquire(path: &Path, timeout: Option<Duration>) -> Result<Self> {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)
                 .with_context(|| format!("failed to create {}", parent.display()))?;
         }
-        let file = OpenOptions::new()
+        let mut file = OpenOptions::new()
             .create(true)
+            .truncate(false)
             .read(true)
             .write(true)
             .open(path)
             .with_context(|| format!("failed to open {}", path.display()))?;
-        file.lock_exclusive()
-            .with_context(|| format!("failed to lock {}", path.display()))?;
-        Ok(Self { _file: file })
+
+        let start = Instant::now();
+        let mut noticed = false;
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(()) => break,
+                Err(err) if err.kind() == fs2::lock_contended_error().kind() => {}
+                Err(err) => {
+                    return Err(err).with_context(|| format!("failed to lock {}", path.display()));
+                }
+            }
+            let waited = start.elapsed();
+            if let Some(timeout) = timeout
+                && waited >= timeout
+            {
+                bail!(Error::LockTimeout(format!(
+                    "timed out after {}s waiting for lock held by {}",
+                    timeout.as_secs(),
+                    describe_holder(path)
+                )));
+            }
+            if !noticed && waited >= NOTICE_AFTER {
+                eprintln!("waiting for lock held by {}", describe_holder(path));
+                noticed = true;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        file.set_len(0)
+            .and_then(|()| file.seek(SeekFrom::Start(0)))
+            .and_then(|_| {
+                write!(
+                    file,
+                    "pid={}\ncommand={}\nstarted={}\n",
+                    process::id(),
+                    current_command(),
+                    history::now()
+                )
+            })
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        // Clear the holder so waiters never report a process that has already let go.
+        self.file.set_len(0).ok();
+    }
+}
+
+struct Holder {
+    pid: u32,
+    command: String,
+    started: Option<u64>,
+}
+
+impl Holder {
+    fn read(path: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        let mut pid = None;
+        let mut command = String::new();
+        let mut started = None;
+        for line in contents.lines() {
+            match line.split_once('=') {
+                Some(("pid", value)) => pid = value.parse().ok(),
+                Some(("command", value)) => command = value.to_string(),
+                Some(("started", value)) => started = value.parse().ok(),
+                _ => {}
+            }
+        }
+        Some(Self {
+            pid: pid?,
+            command,
+            started,
+        })
+    }
+}
+
+fn describe_holder(path: &Path) -> String {
+    let Some(holder) = Holder::read(path) else {
+        return "another process".to_string();
+    };
+    let mut description = format!("pid {} ({})", holder.pid, holder.command);
+    if let Some(started) = holder.started {
+        description.push_str(&format!(", started {}", history::format_age(started)));
+    }
+    if !process_exists(holder.pid) {
+        description.push_str(
+            "; that process no longer exists, so the recorded holder is stale \
+             and the lock is held by another process",
+        );
+    }
+    description
+}
+
+fn current_command() -> String {
+    std::iter::once("worktree".to_string())
+        .chain(env::args().skip(1))
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('\n', " ")
+}
+
+/// Probes with signal 0. `EPERM` means the process exists but belongs to
+/// another user, so only `ESRCH` counts as gone.
+#[cfg(unix)]
+fn process_exists(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return true;
+    };
+    // SAFETY: signal 0 performs only the existence and permission checks.
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
     }
+    std::io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+}
+
+#[cfg(not(unix))]
+fn process_exists(_pid: u32) -> bool {
+    true
 }
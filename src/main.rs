@@ -1,13 +1,22 @@
-use anyhow::Result;
 use clap::Parser;
+use std::process::ExitCode;
 
 mod app;
 mod cli;
 mod config;
+mod error;
 mod git;
+mod history;
 mod lock;
+mod metadata;
 mod repo;
 
-fn main() -> Result<()> {
-    app::run(cli::Cli::parse())
+fn main() -> ExitCode {
+    match app::run(cli::Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            ExitCode::from(error::exit_code(&err))
+        }
+    }
 }
@@ -0,0 +1,121 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Bump when the file layout changes, and teach `migrate` to upgrade older files.
+const SCHEMA_VERSION: u32 = 1;
+
+/// Per-worktree facts, stored as `<name>.toml` under the git common dir so they
+/// never show up in the checkout.
+pub(crate) struct MetadataStore {
+    dir: PathBuf,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Metadata {
+    #[serde(default)]
+    pub(crate) version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) created_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) created_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) tool: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) base: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
+}
+
+impl MetadataStore {
+    pub(crate) fn new(git_common_dir: &Path) -> Self {
+        Self {
+            dir: git_common_dir.join("worktree-meta"),
+        }
+    }
+
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub(crate) fn load(&self, name: &str) -> Result<Option<Metadata>> {
+        let path = self.path(name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let metadata: Metadata = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        if metadata.version > SCHEMA_VERSION {
+            bail!(
+                "{} was written by a newer version of worktree (schema {}, expected {})",
+                path.display(),
+                metadata.version,
+                SCHEMA_VERSION
+            );
+        }
+        Ok(Some(migrate(metadata)))
+    }
+
+    pub(crate) fn save(&self, name: &str, metadata: &Metadata) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create {}", self.dir.display()))?;
+        let metadata = Metadata {
+            version: SCHEMA_VERSION,
+            ..metadata.clone()
+        };
+        let contents = toml::to_string(&metadata).context("failed to serialize metadata")?;
+        let path = self.path(name);
+        let tmp = path.with_extension("toml.tmp");
+        fs::write(&tmp, contents).with_context(|| format!("failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    pub(crate) fn rename(&self, old: &str, new: &str) -> Result<()> {
+        let (old, new) = (self.path(old), self.path(new));
+        if !old.exists() {
+            return Ok(());
+        }
+        fs::rename(&old, &new).with_context(|| format!("failed to write {}", new.display()))
+    }
+
+    pub(crate) fn remove(&self, name: &str) -> Result<()> {
+        let path = self.path(name);
+        if !path.exists() {
+            return Ok(());
+        }
+        fs::remove_file(&path).with_context(|| format!("failed to remove {}", path.display()))
+    }
+
+    /// Names with a stored metadata file.
+    pub(crate) fn names(&self) -> Result<Vec<String>> {
+        if !self.dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut names: Vec<_> = fs::read_dir(&self.dir)?
+            .filter_map(|res| res.ok())
+            .filter_map(|entry| {
+                let file_name = entry.file_name();
+                file_name.to_str()?.strip_suffix(".toml").map(String::from)
+            })
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.toml", name))
+    }
+}
+
+/// Upgrades metadata read from an older schema. Version 0 (no `version` key)
+/// has the same fields as version 1.
+fn migrate(mut metadata: Metadata) -> Metadata {
+    metadata.version = SCHEMA_VERSION;
+    metadata
+}
@@ -1,17 +1,69 @@
+use crate::config::{Config, Placeholders};
+use crate::error::Error;
 use crate::git;
+use crate::history::{self, History};
 use crate::lock::RepoLock;
+use crate::metadata::{Metadata, MetadataStore};
 use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
 use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
 use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::iter;
 use std::path::{Component, Path, PathBuf};
 use std::process;
+use std::sync::Mutex;
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+const ARCHIVE_REFS: &str = "refs/worktree-archive";
+const TRASH_DIR: &str = "worktree-trash";
+const ROOT_FILE: &str = "worktree-tool.root";
 
 pub(crate) struct Repo {
     root: PathBuf,
+    /// What `{repo}` expands to; see `repo_name`.
+    name: String,
     git_common_dir: PathBuf,
+    /// Where git commands run: the main checkout, or the git dir itself for
+    /// bare repos whose root isn't a repository on its own.
+    git_cwd: PathBuf,
     worktrees_dir: PathBuf,
+    bare: bool,
+    /// Whether the git dir lives apart from the checkout, so the checkout
+    /// path is written to `worktree-tool.root` for linked worktrees.
+    separate_git_dir: bool,
+    archive_on_remove: bool,
+    /// How long trashed worktrees are kept; `None` means removals are final.
+    trash_retention_days: Option<u64>,
+    lock_timeout: Option<Duration>,
+    dry_run: bool,
+}
+
+struct TrashEntry {
+    name: String,
+    trashed_at: u64,
+    path: PathBuf,
+}
+
+/// Which worktrees `clear` removes. With no filter set, it removes them all.
+pub(crate) struct ClearFilter {
+    pub(crate) include_locked: bool,
+    pub(crate) older_than: Option<Duration>,
+    pub(crate) merged: Option<String>,
+    pub(crate) clean_only: bool,
+    pub(crate) pattern: Option<String>,
+}
+
+impl ClearFilter {
+    fn is_selective(&self) -> bool {
+        self.older_than.is_some()
+            || self.merged.is_some()
+            || self.clean_only
+            || self.pattern.is_some()
+    }
 }
 
 pub(crate) struct CommandSpec {
@@ -19,6 +71,15 @@ pub(crate) struct CommandSpec {
     pub(crate) args: Vec<String>,
 }
 
+/// How a configured tool (`codex`, `claude`) was launched in a worktree.
+/// `resume` rebuilds its args from the current config, so they follow renames
+/// and config edits. Extra command-line args are left out: they are usually
+/// the first prompt, which must not be sent again.
+pub(crate) struct ToolLaunch {
+    pub(crate) tool: String,
+    pub(crate) profile: Option<String>,
+}
+
 impl CommandSpec {
     pub(crate) fn from_tail(mut tail: Vec<String>) -> Option<Self> {
         if tail.is_empty() {
@@ -33,23 +94,64 @@ impl CommandSpec {
 }
 
 impl Repo {
-    pub(crate) fn try_discover() -> Option<Self> {
-        let git_common_dir = git::stdout(["rev-parse", "--path-format=absolute", "--git-common-dir"]).ok()?;
+    pub(crate) fn try_discover(config: &Config) -> Result<Option<Self>> {
+        let Ok(git_common_dir) =
+            git::stdout(["rev-parse", "--path-format=absolute", "--git-common-dir"])
+        else {
+            return Ok(None);
+        };
         let git_common_dir = PathBuf::from(git_common_dir);
-        let root = git_common_dir.parent()?.to_path_buf();
-        Some(Self {
-            worktrees_dir: root.join(".worktrees"),
+        let Some((root, bare, separate_git_dir)) = discover_root(&git_common_dir)? else {
+            return Ok(None);
+        };
+        let name = repo_name(&root, &git_common_dir, bare);
+        Ok(Some(Self {
+            worktrees_dir: config.worktrees_dir(&root, &name)?,
+            git_cwd: if bare {
+                git_common_dir.clone()
+            } else {
+                root.clone()
+            },
             root,
+            name,
             git_common_dir,
-        })
+            bare,
+            separate_git_dir,
+            archive_on_remove: config.archive_on_remove(),
+            trash_retention_days: config.trash_retention_days(),
+            lock_timeout: None,
+            dry_run: false,
+        }))
     }
 
+    pub(crate) fn with_lock_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    pub(crate) fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Creates a worktree and enters it. `launch` describes the configured
+    /// tool that `command` starts, so it is recorded for `resume`; plain
+    /// commands are run but not recorded.
     pub(crate) fn create_worktree(
         &self,
         name: Option<String>,
         command: Option<CommandSpec>,
+        description: Option<String>,
+        launch: Option<ToolLaunch>,
     ) -> Result<()> {
-        fs::create_dir_all(&self.worktrees_dir)?;
+        if !self.worktrees_dir.is_dir()
+            && !self.skip(format_args!(
+                "create directory {}",
+                self.worktrees_dir.display()
+            ))
+        {
+            fs::create_dir_all(&self.worktrees_dir)?;
+        }
         let name = match name {
             Some(name) => {
                 validate_worktree_name(&name)?;
@@ -60,90 +162,1438 @@ impl Repo {
         let dest = self.worktrees_dir.join(&name);
         if dest.exists() {
             if dest.is_dir() {
-                bail!("worktree '{}' already exists", name);
+                bail!(Error::WorktreeExists(name));
             } else {
-                bail!(
-                    "worktree path exists and is not a directory: {}",
-                    dest.display()
-                );
+                bail!(Error::NotADirectory(dest));
             }
         }
 
         {
-            let _lock = RepoLock::acquire(&self.git_common_dir.join("worktree-tool.lock"))?;
-            git_worktree_add_with_retry(&self.root, &dest)?;
+            let _lock = self.lock()?;
+            self.ensure_worktrees_dir_excluded()?;
+            let start = self.start_point();
+            if let Some(commit) = &start {
+                let mut args = worktree_add_args("--detach", &dest);
+                args.push(commit.into());
+                if !self.skip(format_args!("run: {}", command_line(&self.git_cwd, &args))) {
+                    git_worktree_add_with_retry(&self.git_cwd, &args)?;
+                }
+            } else {
+                self.add_orphan_worktree(&dest)?;
+            }
+            if !self.dry_run {
+                self.metadata().save(
+                    &name,
+                    &Metadata {
+                        created_at: Some(history::now()),
+                        created_by: self.user_name(),
+                        tool: launch.as_ref().map(|launch| launch.tool.clone()),
+                        profile: launch.as_ref().and_then(|launch| launch.profile.clone()),
+                        base: start,
+                        description,
+                        ..Metadata::default()
+                    },
+                )?;
+            }
         }
 
+        if self.dry_run {
+            match &command {
+                Some(command) => println!(
+                    "would run in {}: {}",
+                    dest.display(),
+                    shell_words(iter::once(&command.program).chain(&command.args))
+                ),
+                None => println!("would start a shell in {}", dest.display()),
+            }
+            return Ok(());
+        }
         self.enter_worktree(&dest, command)
     }
 
-    pub(crate) fn switch_worktree(&self, name: String, command: Option<CommandSpec>) -> Result<()> {
-        validate_worktree_name(&name)?;
+    pub(crate) fn move_worktree(&self, old: &str, new: &str) -> Result<()> {
+        let old = self.resolve_exact_name(old)?;
+        validate_worktree_name(new)?;
+        let source = self.worktrees_dir.join(&old);
+        let dest = self.worktrees_dir.join(new);
+        if dest.exists() {
+            bail!(Error::WorktreeExists(new.to_string()));
+        }
+        if self.locked_worktrees()?.contains_key(&old) {
+            bail!("worktree '{}' is locked; unlock it first", old);
+        }
+
+        let _lock = self.lock()?;
+        let args: Vec<OsString> = vec![
+            "worktree".into(),
+            "move".into(),
+            source.clone().into(),
+            dest.clone().into(),
+        ];
+        if !self.skip(format_args!("run: {}", command_line(&self.git_cwd, &args))) {
+            git::run_in(&self.git_cwd, &args)?;
+        }
+
+        // Only a checked-out branch with exactly the worktree's name is renamed,
+        // such as the one `add_orphan_worktree` starts on or one the user named
+        // after it; any other branch is the user's and stays as it is.
+        let worktree = if self.dry_run { &source } else { &dest };
+        let branch = git::run_in(worktree, ["symbolic-ref", "--quiet", "--short", "HEAD"]);
+        if branch.is_ok_and(|branch| branch == old) {
+            let args: Vec<OsString> = vec!["branch".into(), "-m".into(), (&old).into(), new.into()];
+            if !self.skip(format_args!("run: {}", command_line(worktree, &args))) {
+                git::run_in(worktree, &args)?;
+                println!("renamed branch '{}' to '{}'", old, new);
+            }
+        }
+
+        if self.skip(format_args!("rename '{}' to '{}' in the history", old, new)) {
+            return Ok(());
+        }
+        History::new(&self.git_common_dir).rename(&old, new)?;
+        self.metadata().rename(&old, new)?;
+        println!("moved worktree '{}' to '{}'", old, new);
+        Ok(())
+    }
+
+    pub(crate) fn remove_worktree(&self, name: &str, force: bool) -> Result<()> {
+        let name = self.resolve_exact_name(name)?;
+        let locked = self.locked_worktrees()?.contains_key(&name);
+        if locked && !force {
+            bail!(
+                "worktree '{}' is locked; unlock it first or pass --force",
+                name
+            );
+        }
+
+        let _lock = self.lock()?;
+        self.purge_expired_trash()?;
+        self.discard_worktree(&name, force, locked)?;
+        self.remove_dir_if_empty(&self.worktrees_dir)?;
+        self.remove_empty_git_dirs()?;
+        // A trashed worktree already reported the move and can come back.
+        if !self.dry_run && self.trash_retention_days.is_none() {
+            println!("removed worktree '{}'", name);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn switch_worktree(
+        &self,
+        name: Option<String>,
+        command: Option<CommandSpec>,
+    ) -> Result<()> {
+        let name = self.switch_target(name.as_deref())?;
         let dest = self.worktrees_dir.join(&name);
         if dest.is_dir() {
             self.enter_worktree(&dest, command)
         } else {
-            bail!("worktree '{}' does not exist", name);
+            bail!(Error::WorktreeMissing(name));
         }
     }
 
-    pub(crate) fn list(&self) -> Result<()> {
-        if !self.worktrees_dir.exists() {
+    /// The worktree `switch` enters: `-` for the previous one, a name or
+    /// shorthand, or an interactive pick when no name is given.
+    pub(crate) fn switch_target(&self, name: Option<&str>) -> Result<String> {
+        match name {
+            Some("-") => self.previous_worktree(),
+            Some(name) => self.resolve_worktree_name(name),
+            None => pick_worktree(self.worktree_names()?),
+        }
+    }
+
+    /// The name `create_worktree` would use: `name` if valid, else the next `N-wt`.
+    pub(crate) fn new_worktree_name(&self, name: Option<String>) -> Result<String> {
+        match name {
+            Some(name) => {
+                validate_worktree_name(&name)?;
+                Ok(name)
+            }
+            None => next_worktree_name(&self.worktrees_dir),
+        }
+    }
+
+    /// Placeholder values for the existing worktree `name`.
+    pub(crate) fn placeholders(&self, name: &str) -> Result<Placeholders> {
+        let path = self.worktrees_dir.join(name);
+        let branch =
+            git::run_in(&path, ["symbolic-ref", "--quiet", "--short", "HEAD"]).unwrap_or_default();
+        let base = match self
+            .metadata()
+            .load(name)?
+            .and_then(|metadata| metadata.base)
+        {
+            Some(base) => base,
+            None => {
+                git::run_in(&path, ["rev-parse", "--verify", "--quiet", "HEAD"]).unwrap_or_default()
+            }
+        };
+        Ok(self.placeholders_with(name, branch, base))
+    }
+
+    /// Placeholder values for a worktree `create_worktree` is about to add: it
+    /// starts detached at `start_point`, or on an orphan branch named after it
+    /// when HEAD is unborn. Any metadata left under the name (say, from a
+    /// trashed worktree) describes something else and is ignored.
+    pub(crate) fn new_placeholders(&self, name: &str) -> Placeholders {
+        let (branch, base) = match self.start_point() {
+            Some(commit) => (String::new(), commit),
+            None => (name.to_string(), String::new()),
+        };
+        self.placeholders_with(name, branch, base)
+    }
+
+    fn placeholders_with(&self, name: &str, branch: String, base: String) -> Placeholders {
+        Placeholders {
+            name: name.to_string(),
+            path: self.worktrees_dir.join(name).display().to_string(),
+            root: self.root.display().to_string(),
+            repo: self.name.clone(),
+            branch,
+            base,
+        }
+    }
+
+    /// Remembers the tool a worktree was launched with, for `resume`.
+    pub(crate) fn record_tool(&self, name: &str, launch: ToolLaunch) -> Result<()> {
+        let name = self.resolve_worktree_name(name)?;
+        if self.dry_run {
             return Ok(());
         }
-        let mut entries: Vec<_> = fs::read_dir(&self.worktrees_dir)?
-            .filter_map(|res| res.ok())
-            .map(|entry| entry.path())
-            .filter(|path| path.is_dir())
+        let store = self.metadata();
+        let mut metadata = store.load(&name)?.unwrap_or_default();
+        metadata.tool = Some(launch.tool);
+        metadata.profile = launch.profile;
+        store.save(&name, &metadata)
+    }
+
+    /// The resolved worktree name and how its tool was last launched.
+    pub(crate) fn recorded_tool(&self, name: &str) -> Result<(String, ToolLaunch)> {
+        let name = self.resolve_worktree_name(name)?;
+        let metadata = self.metadata().load(&name)?.unwrap_or_default();
+        let Some(tool) = metadata.tool else {
+            bail!(
+                "no tool recorded for worktree '{}'; launch one with e.g. `worktree claude switch {}`",
+                name,
+                name
+            );
+        };
+        Ok((
+            name,
+            ToolLaunch {
+                tool,
+                profile: metadata.profile,
+            },
+        ))
+    }
+
+    pub(crate) fn doctor(&self) -> Result<()> {
+        let mut problems = 0;
+        if let Some(entry) = self.exclude_entry()? {
+            if self.is_ignored(&entry)? {
+                println!("ok: {} is ignored by git", entry);
+            } else {
+                problems += 1;
+                println!(
+                    "problem: {} is not ignored, so it shows up as untracked in `git status`; \
+                     add `{}` to {}",
+                    entry,
+                    entry,
+                    self.git_common_dir.join("info/exclude").display()
+                );
+            }
+        }
+        if problems > 0 {
+            bail!("found {} problem(s)", problems);
+        }
+        Ok(())
+    }
+
+    /// Reconciles the worktrees directory with git's worktree list: prunes
+    /// stale entries, removes leftover directories and reports what it did.
+    pub(crate) fn gc(&self) -> Result<()> {
+        let _lock = self.lock()?;
+        let mut stale = Vec::new();
+        let mut live = Vec::new();
+        for worktree in git::worktree_list(&self.git_cwd)? {
+            match worktree.prunable {
+                Some(reason) if reason.is_empty() => {
+                    stale.push(worktree.path.display().to_string())
+                }
+                Some(reason) => stale.push(format!("{} ({})", worktree.path.display(), reason)),
+                None => live.push(worktree.path),
+            }
+        }
+        let mut fixed = stale.len();
+        let prune = [OsString::from("worktree"), OsString::from("prune")];
+        if !stale.is_empty()
+            && !self.skip(format_args!("run: {}", command_line(&self.git_cwd, &prune)))
+        {
+            git::run_in(&self.git_cwd, &prune)?;
+            for entry in &stale {
+                println!("pruned stale entry for {}", entry);
+            }
+        }
+
+        if self.worktrees_dir.is_dir() {
+            for entry in fs::read_dir(&self.worktrees_dir)? {
+                let path = entry?.path();
+                if !path.is_dir() || live.iter().any(|live| same_path(live, &path)) {
+                    continue;
+                }
+                // Only an empty directory is safe debris from an interrupted
+                // create or remove. A checkout with a broken link may still hold
+                // work, and a healthy one belongs to another repo sharing the
+                // directory; anything else may be someone's files.
+                if path.join(".git").exists() {
+                    if let Some(problem) = worktree_link_problem(&path) {
+                        println!(
+                            "leaving unregistered checkout {} ({}); run `worktree repair` if it belongs to this repo",
+                            path.display(),
+                            problem
+                        );
+                    }
+                    continue;
+                }
+                if fs::read_dir(&path)?.next().is_some() {
+                    println!("leaving unregistered directory {}", path.display());
+                    continue;
+                }
+                fixed += 1;
+                if !self.skip(format_args!("remove directory {}", path.display())) {
+                    fs::remove_dir(&path)
+                        .with_context(|| format!("failed to remove {}", path.display()))?;
+                    println!("removed leftover directory {}", path.display());
+                }
+            }
+        }
+
+        let metadata = self.metadata();
+        let names = self.worktree_names()?;
+        let trashed: Vec<_> = self
+            .trash_entries()?
+            .into_iter()
+            .map(|entry| entry.name)
             .collect();
-        entries.sort();
+        for name in metadata.names()? {
+            if names.contains(&name) || trashed.contains(&name) {
+                continue;
+            }
+            fixed += 1;
+            if !self.skip(format_args!(
+                "remove metadata for missing worktree '{}'",
+                name
+            )) {
+                metadata.remove(&name)?;
+                println!("removed metadata for missing worktree '{}'", name);
+            }
+        }
+
+        self.remove_empty_git_dirs()?;
+        self.remove_dir_if_empty(&self.worktrees_dir)?;
+        if fixed == 0 {
+            println!("nothing to clean up");
+        }
+        Ok(())
+    }
+
+    pub(crate) fn list(&self, all: bool) -> Result<()> {
+        let locked = self.locked_worktrees()?;
+        let metadata = self.metadata();
+        for name in self.worktree_names()? {
+            let mut line = match locked.get(&name) {
+                Some(reason) if reason.is_empty() => format!("{}  (locked)", name),
+                Some(reason) => format!("{}  (locked: {})", name, reason),
+                None => name.clone(),
+            };
+            if let Some(description) = metadata
+                .load(&name)?
+                .and_then(|metadata| metadata.description)
+            {
+                line.push_str("  ");
+                line.push_str(&description);
+            }
+            println!("{}", line);
+        }
+        if all {
+            for worktree in self.foreign_worktrees()? {
+                let path = worktree.path.display();
+                match worktree.locked.as_deref() {
+                    Some("") => println!("{}  (foreign, locked)", path),
+                    Some(reason) => println!("{}  (foreign, locked: {})", path, reason),
+                    None => println!("{}  (foreign)", path),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn info(&self, name: &str) -> Result<()> {
+        let name = self.resolve_worktree_name(name)?;
+        let path = self.worktrees_dir.join(&name);
+        let metadata = self.metadata().load(&name)?.unwrap_or_default();
+        let field = |label: &str, value: &dyn fmt::Display| println!("{:<11} {}", label, value);
+
+        field("name:", &name);
+        field("path:", &path.display());
+        if let Some(description) = &metadata.description {
+            field("about:", description);
+        }
+        let head = git::run_in(&path, ["log", "-1", "--format=%h %s"]).ok();
+        field("HEAD:", &head.as_deref().unwrap_or("(no commits)"));
+        let branch = git::run_in(&path, ["symbolic-ref", "--quiet", "--short", "HEAD"]).ok();
+        field("branch:", &branch.as_deref().unwrap_or("(detached)"));
+        if let Some(base) = &metadata.base {
+            let range = format!("HEAD...{}", base);
+            let counts = git::run_in(&path, ["rev-list", "--left-right", "--count", &range]);
+            let short = &base[..base.len().min(7)];
+            match counts
+                .as_deref()
+                .ok()
+                .and_then(|counts| counts.split_once('\t'))
+            {
+                Some((ahead, behind)) => field(
+                    "base:",
+                    &format_args!("{} ({} ahead, {} behind)", short, ahead, behind),
+                ),
+                None => field("base:", &short),
+            }
+        }
+
+        let status = git::run_in(&path, ["status", "--porcelain=v2"])?;
+        let (mut staged, mut modified, mut untracked) = (0, 0, 0);
+        for line in status.lines() {
+            match line.split(' ').take(2).collect::<Vec<_>>()[..] {
+                ["?", _] => untracked += 1,
+                ["1" | "2" | "u", codes] => {
+                    let mut codes = codes.chars();
+                    staged += usize::from(codes.next().is_some_and(|code| code != '.'));
+                    modified += usize::from(codes.next().is_some_and(|code| code != '.'));
+                }
+                _ => {}
+            }
+        }
+        field(
+            "changes:",
+            &format_args!(
+                "{} staged, {} modified, {} untracked",
+                staged, modified, untracked
+            ),
+        );
+        match self.locked_worktrees()?.get(&name) {
+            Some(reason) if reason.is_empty() => field("locked:", &"yes"),
+            Some(reason) => field("locked:", &format_args!("yes ({})", reason)),
+            None => field("locked:", &"no"),
+        }
+        field("disk:", &format_size(disk_usage(&path)));
+
+        if let Some(tool) = &metadata.tool {
+            match &metadata.profile {
+                Some(profile) => field("tool:", &format_args!("{} (profile {})", tool, profile)),
+                None => field("tool:", tool),
+            }
+        }
+        if let Some(created_at) = metadata.created_at {
+            match &metadata.created_by {
+                Some(user) => field(
+                    "created:",
+                    &format_args!("{} by {}", history::format_age(created_at), user),
+                ),
+                None => field("created:", &history::format_age(created_at)),
+            }
+        }
+        let entered = History::new(&self.git_common_dir)
+            .entries()?
+            .into_iter()
+            .find(|entry| entry.name == name);
+        match entered {
+            Some(entry) => field("entered:", &history::format_age(entry.entered_at)),
+            None => field("entered:", &"never"),
+        }
+        match processes_in(&path) {
+            Some(processes) if processes.is_empty() => field("processes:", &"none"),
+            Some(processes) => field("processes:", &processes.join(", ")),
+            None => field("processes:", &"unknown on this platform"),
+        }
+        Ok(())
+    }
+
+    pub(crate) fn adopt_worktree(&self, path: &Path, name: Option<String>) -> Result<()> {
+        let Some(worktree) = self
+            .foreign_worktrees()?
+            .into_iter()
+            .find(|worktree| same_path(&worktree.path, path))
+        else {
+            bail!(
+                "{} is not a worktree of this repository outside {}",
+                path.display(),
+                self.worktrees_dir.display()
+            );
+        };
+        if worktree.locked.is_some() {
+            bail!("worktree {} is locked; unlock it first", path.display());
+        }
+        let name = match name {
+            Some(name) => name,
+            None => worktree
+                .path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(String::from)
+                .ok_or_else(|| Error::InvalidName(worktree.path.display().to_string()))?,
+        };
+        validate_worktree_name(&name)?;
+        let dest = self.worktrees_dir.join(&name);
+        if dest.exists() {
+            bail!(Error::WorktreeExists(name));
+        }
+
+        let _lock = self.lock()?;
+        let args: Vec<OsString> = vec![
+            "worktree".into(),
+            "move".into(),
+            worktree.path.clone().into(),
+            dest.into(),
+        ];
+        if self.skip(format_args!("run: {}", command_line(&self.git_cwd, &args))) {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.worktrees_dir)?;
+        self.ensure_worktrees_dir_excluded()?;
+        git::run_in(&self.git_cwd, &args)?;
+        let dest = self.worktrees_dir.join(&name);
+        self.ensure_metadata(
+            &name,
+            git::run_in(&dest, ["rev-parse", "--verify", "--quiet", "HEAD"]).ok(),
+        )?;
+        println!(
+            "adopted worktree '{}' from {}",
+            name,
+            worktree.path.display()
+        );
+        Ok(())
+    }
+
+    /// Re-links managed worktrees with the common dir after the repository
+    /// (or the worktrees directory) was moved.
+    pub(crate) fn repair(&self) -> Result<()> {
+        // Broken links drop a checkout from `git worktree list`, so look at
+        // the directory itself rather than `worktree_names`.
+        let mut names: Vec<_> = match fs::read_dir(&self.worktrees_dir) {
+            Ok(entries) => entries
+                .filter_map(|res| res.ok())
+                .map(|entry| entry.path())
+                .filter(|path| self.is_own_checkout(path))
+                .filter_map(|path| path.file_name()?.to_str().map(String::from))
+                .collect(),
+            Err(_) => vec![],
+        };
+        names.sort();
+        let broken: Vec<_> = names
+            .iter()
+            .filter_map(|name| {
+                let problem = worktree_link_problem(&self.worktrees_dir.join(name))?;
+                Some((name, problem))
+            })
+            .collect();
+        if broken.is_empty() {
+            println!("all {} worktree(s) are linked correctly", names.len());
+            return Ok(());
+        }
+
+        let _lock = self.lock()?;
+        let mut args: Vec<OsString> = vec!["worktree".into(), "repair".into()];
+        args.extend(
+            broken
+                .iter()
+                .map(|(name, _)| self.worktrees_dir.join(name).into_os_string()),
+        );
+        if self.skip(format_args!("run: {}", command_line(&self.git_cwd, &args))) {
+            return Ok(());
+        }
+        // git reports what it could not fix on stderr; the check below covers it.
+        git::run_in(&self.git_cwd, &args).ok();
+
+        let mut failed = 0;
+        for (name, before) in broken {
+            match worktree_link_problem(&self.worktrees_dir.join(name)) {
+                None => println!("repaired '{}' ({})", name, before),
+                Some(problem) => {
+                    failed += 1;
+                    println!("could not repair '{}': {}", name, problem);
+                }
+            }
+        }
+        if failed > 0 {
+            bail!("{} worktree(s) could not be repaired", failed);
+        }
+        Ok(())
+    }
+
+    /// Linked worktrees living outside the worktrees directory; the main
+    /// checkout is not one of them.
+    /// Worktrees of this repo created outside the worktrees directory. Trashed
+    /// worktrees are still registered with git but belong to `trash`.
+    fn foreign_worktrees(&self) -> Result<Vec<git::WorktreeEntry>> {
+        let trash_dir = self.git_common_dir.join(TRASH_DIR);
+        Ok(git::worktree_list(&self.git_cwd)?
+            .into_iter()
+            .skip(1)
+            .filter(|worktree| {
+                !worktree.bare
+                    && !worktree.path.starts_with(&self.worktrees_dir)
+                    && !worktree.path.starts_with(&trash_dir)
+            })
+            .collect())
+    }
+
+    pub(crate) fn archive_worktree(&self, name: &str) -> Result<()> {
+        let name = self.resolve_exact_name(name)?;
+        self.archive(&name)
+    }
+
+    pub(crate) fn restore_worktree(&self, archive: &str, name: Option<String>) -> Result<()> {
+        let reference = self.resolve_archive(archive)?;
+        let name = match name {
+            Some(name) => name,
+            None => reference.rsplit('/').nth(1).unwrap_or(archive).to_string(),
+        };
+        validate_worktree_name(&name)?;
+        let dest = self.worktrees_dir.join(&name);
+        if dest.exists() {
+            bail!(Error::WorktreeExists(name));
+        }
+        if self.skip(format_args!(
+            "restore {} into {}",
+            reference,
+            dest.display()
+        )) {
+            return Ok(());
+        }
+
+        // Check out the commit the worktree was on, then lay the archived
+        // working tree over it so changes come back unstaged.
+        let parent = format!("{}^", reference);
+        let base =
+            if git::run_in(&self.git_cwd, ["rev-parse", "--verify", "--quiet", &parent]).is_ok() {
+                parent
+            } else {
+                reference.clone()
+            };
+        fs::create_dir_all(&self.worktrees_dir)?;
+        {
+            let _lock = self.lock()?;
+            self.ensure_worktrees_dir_excluded()?;
+            let mut args = worktree_add_args("--detach", &dest);
+            args.push(base.into());
+            git_worktree_add_with_retry(&self.git_cwd, &args)?;
+        }
+        git::run_in(&dest, ["read-tree", "--reset", "-u", &reference])?;
+        git::run_in(&dest, ["reset", "--quiet"])?;
+        self.ensure_metadata(
+            &name,
+            git::run_in(&dest, ["rev-parse", "--verify", "--quiet", "HEAD"]).ok(),
+        )?;
+        println!("restored worktree '{}' from {}", name, reference);
+        self.enter_worktree(&dest, None)
+    }
+
+    pub(crate) fn lock_worktree(&self, name: &str, reason: Option<String>) -> Result<()> {
+        let name = self.resolve_worktree_name(name)?;
+        let dest = self.worktrees_dir.join(&name);
+        let mut args: Vec<OsString> = vec!["worktree".into(), "lock".into()];
+        if let Some(reason) = reason {
+            args.push("--reason".into());
+            args.push(reason.into());
+        }
+        args.push(dest.into_os_string());
+        if self.skip(format_args!("run: {}", command_line(&self.git_cwd, &args))) {
+            return Ok(());
+        }
+        let _lock = self.lock()?;
+        git::run_in(&self.git_cwd, args)?;
+        println!("locked worktree '{}'", name);
+        Ok(())
+    }
+
+    pub(crate) fn unlock_worktree(&self, name: &str) -> Result<()> {
+        let name = self.resolve_worktree_name(name)?;
+        let dest = self.worktrees_dir.join(&name);
+        let args: Vec<OsString> = vec!["worktree".into(), "unlock".into(), dest.into()];
+        if self.skip(format_args!("run: {}", command_line(&self.git_cwd, &args))) {
+            return Ok(());
+        }
+        let _lock = self.lock()?;
+        git::run_in(&self.git_cwd, args)?;
+        println!("unlocked worktree '{}'", name);
+        Ok(())
+    }
+
+    pub(crate) fn recent(&self) -> Result<()> {
+        let names = self.worktree_names()?;
+        let entries: Vec<_> = History::new(&self.git_common_dir)
+            .entries()?
+            .into_iter()
+            .filter(|entry| names.contains(&entry.name))
+            .collect();
+        let width = entries
+            .iter()
+            .map(|entry| entry.name.len())
+            .max()
+            .unwrap_or(0);
         for entry in entries {
-            if let Some(name) = entry.file_name().and_then(|n| n.to_str()) {
-                println!("{}", name);
+            println!(
+                "{:width$}  {}",
+                entry.name,
+                history::format_age(entry.entered_at)
+            );
+        }
+        Ok(())
+    }
+
+    pub(crate) fn exec(
+        &self,
+        command: CommandSpec,
+        parallel: usize,
+        filter: Option<&str>,
+    ) -> Result<()> {
+        let names: Vec<_> = self
+            .worktree_names()?
+            .into_iter()
+            .filter(|name| filter.is_none_or(|pattern| glob_match(pattern, name)))
+            .collect();
+        if names.is_empty() {
+            println!("no worktrees to run in");
+            return Ok(());
+        }
+
+        let queue = Mutex::new(names.iter());
+        let results = Mutex::new(Vec::new());
+        thread::scope(|scope| {
+            for _ in 0..parallel.min(names.len()) {
+                scope.spawn(|| {
+                    loop {
+                        let Some(name) = queue.lock().unwrap().next() else {
+                            break;
+                        };
+                        let dest = self.worktrees_dir.join(name);
+                        let outcome = if parallel > 1 {
+                            run_buffered(name, &dest, &command)
+                        } else {
+                            println!("==> {} <==", name);
+                            run_inherited(&dest, &command)
+                        };
+                        results.lock().unwrap().push((name, outcome));
+                    }
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by(|a, b| a.0.cmp(b.0));
+        let width = results
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        println!();
+        let mut failed = 0;
+        for (name, outcome) in &results {
+            if !matches!(outcome, ExecOutcome::Passed) {
+                failed += 1;
             }
+            println!("{:width$}  {}", name, outcome);
+        }
+        if failed > 0 {
+            bail!(Error::ExecFailed {
+                failed,
+                total: results.len(),
+            });
         }
         Ok(())
     }
 
-    pub(crate) fn clear(&self) -> Result<()> {
-        env::set_current_dir(&self.root)?;
+    pub(crate) fn clear(&self, filter: &ClearFilter) -> Result<()> {
+        if !self.dry_run {
+            env::set_current_dir(&self.root)?;
+        }
 
         {
-            let _lock = RepoLock::acquire(&self.git_common_dir.join("worktree-tool.lock"))?;
+            let _lock = self.lock()?;
 
-            for worktree in git::worktree_paths(&self.root)? {
-                if worktree.starts_with(&self.worktrees_dir) {
-                    git_worktree_remove_with_retry(&self.root, &worktree)?;
+            if let Some(target) = &filter.merged {
+                let commit = format!("{}^{{commit}}", target);
+                git::run_in(&self.git_cwd, ["rev-parse", "--verify", "--quiet", &commit])
+                    .with_context(|| format!("unknown branch '{}'", target))?;
+            }
+            self.purge_expired_trash()?;
+            let mut selected = Vec::new();
+            for worktree in git::worktree_list(&self.git_cwd)? {
+                if !worktree.path.starts_with(&self.worktrees_dir) {
+                    continue;
+                }
+                let name = worktree.path.file_name().unwrap_or_default();
+                if let Some(reason) = &worktree.locked
+                    && !filter.include_locked
+                {
+                    match reason.as_str() {
+                        "" => println!("skipping locked worktree '{}'", name.display()),
+                        reason => {
+                            println!("skipping locked worktree '{}' ({})", name.display(), reason)
+                        }
+                    }
+                    continue;
                 }
+                if !self.clear_selects(filter, &worktree.path)? {
+                    continue;
+                }
+                selected.push((
+                    name.to_string_lossy().into_owned(),
+                    worktree.locked.is_some(),
+                ));
             }
 
-            if self.worktrees_dir.exists() {
-                fs::remove_dir_all(&self.worktrees_dir)?;
+            if filter.is_selective() {
+                if selected.is_empty() {
+                    println!("no worktrees match");
+                    return Ok(());
+                }
+                println!("removing {} worktree(s):", selected.len());
+                for (name, _) in &selected {
+                    println!("  {}", name);
+                }
+            }
+            for (name, locked) in &selected {
+                self.discard_worktree(name, true, *locked)?;
             }
 
+            // The worktrees directory may be shared with other repos or hold
+            // the user's own files, so only drop it once it is empty.
+            self.remove_dir_if_empty(&self.worktrees_dir)?;
+
+            self.remove_empty_git_dirs()?;
+        }
+
+        if self.skip(format_args!("start a shell in {}", self.root.display())) {
+            return Ok(());
+        }
+        run_shell(&self.root)?;
+        Ok(())
+    }
+
+    /// Whether a worktree passes `clear`'s age, merge, cleanliness and name filters.
+    fn clear_selects(&self, filter: &ClearFilter, path: &Path) -> Result<bool> {
+        if let Some(pattern) = &filter.pattern {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !glob_match(pattern, &name) {
+                return Ok(false);
+            }
+        }
+        if let Some(age) = filter.older_than {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let created_at = match self
+                .metadata()
+                .load(&name)?
+                .and_then(|metadata| metadata.created_at)
+            {
+                Some(created_at) => created_at,
+                // Without metadata, fall back to the `.git` file: `git worktree
+                // add` writes it once, so its mtime is when the worktree was created.
+                None => fs::metadata(path.join(".git"))
+                    .and_then(|metadata| metadata.modified())
+                    .with_context(|| format!("failed to read {}", path.join(".git").display()))?
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_secs()),
+            };
+            if history::now().saturating_sub(created_at) < age.as_secs() {
+                return Ok(false);
+            }
+        }
+        if let Some(target) = &filter.merged {
+            let Ok(head) = git::run_in(path, ["rev-parse", "--verify", "--quiet", "HEAD"]) else {
+                return Ok(false);
+            };
+            if git::run_in(
+                &self.git_cwd,
+                ["merge-base", "--is-ancestor", &head, target],
+            )
+            .is_err()
+            {
+                return Ok(false);
+            }
+        }
+        if filter.clean_only && !git::run_in(path, ["status", "--porcelain"])?.is_empty() {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Archives the worktree if configured, then moves it to the trash or
+    /// removes it. git wants `--force` to drop local changes, and a second one
+    /// to touch a locked worktree.
+    fn discard_worktree(&self, name: &str, force: bool, locked: bool) -> Result<()> {
+        let dest = self.worktrees_dir.join(name);
+        if self.archive_on_remove {
+            self.archive(name)?;
+        }
+        if self.trash_retention_days.is_some() {
+            return self.move_to_trash(name, &dest, locked);
+        }
+        let args = worktree_remove_args(&dest, usize::from(force) + usize::from(locked));
+        if !self.skip(format_args!("run: {}", command_line(&self.git_cwd, &args))) {
+            git_worktree_remove_with_retry(&self.git_cwd, &dest, &args)?;
+            self.metadata().remove(name)?;
+        }
+        Ok(())
+    }
+
+    /// Moves a worktree under `$GIT_COMMON_DIR/worktree-trash/<timestamp>-<name>`
+    /// with `git worktree move`, so git keeps tracking it and it can be moved back.
+    fn move_to_trash(&self, name: &str, dest: &Path, locked: bool) -> Result<()> {
+        let trash_dir = self.git_common_dir.join(TRASH_DIR);
+        let target = trash_dir.join(format!("{}-{}", history::now(), name));
+        let mut args: Vec<OsString> = vec!["worktree".into(), "move".into()];
+        if locked {
+            args.extend(["--force".into(), "--force".into()]);
+        }
+        args.extend([dest.into(), target.into()]);
+        if self.skip(format_args!("run: {}", command_line(&self.git_cwd, &args))) {
+            return Ok(());
+        }
+        fs::create_dir_all(&trash_dir)
+            .with_context(|| format!("failed to create {}", trash_dir.display()))?;
+        git::run_in(&self.git_cwd, &args)?;
+        println!("moved worktree '{}' to the trash", name);
+        Ok(())
+    }
+
+    pub(crate) fn trash_list(&self) -> Result<()> {
+        let entries = self.trash_entries()?;
+        let width = entries
+            .iter()
+            .map(|entry| entry.name.len())
+            .max()
+            .unwrap_or(0);
+        for entry in entries {
+            println!(
+                "{:width$}  trashed {}",
+                entry.name,
+                history::format_age(entry.trashed_at)
+            );
+        }
+        Ok(())
+    }
+
+    pub(crate) fn trash_restore(&self, name: &str) -> Result<()> {
+        let Some(entry) = self
+            .trash_entries()?
+            .into_iter()
+            .find(|entry| entry.name == name)
+        else {
+            bail!("no trashed worktree named '{}'", name);
+        };
+        let dest = self.worktrees_dir.join(&entry.name);
+        if dest.exists() {
+            bail!(Error::WorktreeExists(entry.name));
+        }
+        // git keeps the lock of a worktree trashed with `--force --force`, and
+        // needs the same to move it back.
+        let locked = git::worktree_list(&self.git_cwd)?
+            .iter()
+            .any(|worktree| same_path(&worktree.path, &entry.path) && worktree.locked.is_some());
+        let mut args: Vec<OsString> = vec!["worktree".into(), "move".into()];
+        if locked {
+            args.extend(["--force".into(), "--force".into()]);
+        }
+        args.extend([entry.path.into(), dest.into()]);
+        if self.skip(format_args!("run: {}", command_line(&self.git_cwd, &args))) {
+            return Ok(());
+        }
+        let _lock = self.lock()?;
+        fs::create_dir_all(&self.worktrees_dir)?;
+        git::run_in(&self.git_cwd, &args)?;
+        self.remove_dir_if_empty(&self.git_common_dir.join(TRASH_DIR))?;
+        let base = git::run_in(
+            &self.worktrees_dir.join(&entry.name),
+            ["rev-parse", "--verify", "--quiet", "HEAD"],
+        )
+        .ok();
+        self.ensure_metadata(&entry.name, base)?;
+        println!("restored worktree '{}'", entry.name);
+        Ok(())
+    }
+
+    pub(crate) fn trash_empty(&self) -> Result<()> {
+        let _lock = self.lock()?;
+        for entry in self.trash_entries()? {
+            self.remove_trash_entry(&entry)?;
+        }
+        self.remove_dir_if_empty(&self.git_common_dir.join(TRASH_DIR))?;
+        self.remove_empty_git_dirs()
+    }
+
+    /// Drops trashed worktrees older than the configured retention period.
+    fn purge_expired_trash(&self) -> Result<()> {
+        let Some(days) = self.trash_retention_days else {
+            return Ok(());
+        };
+        let cutoff = history::now().saturating_sub(days * 24 * 60 * 60);
+        for entry in self.trash_entries()? {
+            if entry.trashed_at < cutoff {
+                self.remove_trash_entry(&entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_trash_entry(&self, entry: &TrashEntry) -> Result<()> {
+        let registered = git::worktree_list(&self.git_cwd)?
+            .iter()
+            .any(|worktree| same_path(&worktree.path, &entry.path));
+        if registered {
+            let args = worktree_remove_args(&entry.path, 2);
+            if !self.skip(format_args!("run: {}", command_line(&self.git_cwd, &args))) {
+                git_worktree_remove_with_retry(&self.git_cwd, &entry.path, &args)?;
+            }
+        } else if !self.skip(format_args!("remove directory {}", entry.path.display())) {
+            fs::remove_dir_all(&entry.path)
+                .with_context(|| format!("failed to remove {}", entry.path.display()))?;
+        }
+        if !self.dry_run {
+            // Keep the metadata if a new worktree has taken the name since.
+            if !self.worktrees_dir.join(&entry.name).exists() {
+                self.metadata().remove(&entry.name)?;
+            }
+            println!("deleted trashed worktree '{}'", entry.name);
+        }
+        Ok(())
+    }
+
+    /// Trashed worktrees, newest first.
+    fn trash_entries(&self) -> Result<Vec<TrashEntry>> {
+        let trash_dir = self.git_common_dir.join(TRASH_DIR);
+        if !trash_dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut entries: Vec<_> = fs::read_dir(&trash_dir)?
+            .filter_map(|res| res.ok())
+            .filter_map(|entry| {
+                let file_name = entry.file_name();
+                let (trashed_at, name) = file_name.to_str()?.split_once('-')?;
+                Some(TrashEntry {
+                    name: name.to_string(),
+                    trashed_at: trashed_at.parse().ok()?,
+                    path: entry.path(),
+                })
+            })
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.trashed_at));
+        Ok(entries)
+    }
+
+    /// Snapshots the worktree's HEAD plus its working tree and untracked files
+    /// (much like `git stash -u`) into `refs/worktree-archive/<name>/<timestamp>`,
+    /// with a `-<n>` suffix for repeat archives within the same second.
+    /// The archive commit's parent is the old HEAD, so detached commits stay
+    /// reachable too. A worktree whose directory is gone has nothing to
+    /// snapshot and is skipped with a note.
+    fn archive(&self, name: &str) -> Result<()> {
+        let dest = self.worktrees_dir.join(name);
+        if !dest.is_dir() {
+            println!(
+                "skipped archiving worktree '{}': {} is missing",
+                name,
+                dest.display()
+            );
+            return Ok(());
+        }
+        let stamp = history::now();
+        if self.skip(format_args!(
+            "archive {} to {}/{}/{}",
+            dest.display(),
+            ARCHIVE_REFS,
+            name,
+            stamp
+        )) {
+            return Ok(());
+        }
+        let head = git::run_in(&dest, ["rev-parse", "--verify", "--quiet", "HEAD"]).ok();
+        let index = self
+            .git_common_dir
+            .join(format!("worktree-tool-archive-{}.index", process::id()));
+        let envs = [("GIT_INDEX_FILE", index.as_os_str())];
+        let tree = (|| {
+            if let Some(head) = &head {
+                git::run_in_env(&dest, &envs, ["read-tree", head])?;
+            }
+            git::run_in_env(&dest, &envs, ["add", "--all"])?;
+            git::run_in_env(&dest, &envs, ["write-tree"])
+        })();
+        fs::remove_file(&index).ok();
+        let tree = tree?;
+
+        let message = format!("worktree archive of {}", name);
+        let mut args = vec!["commit-tree", &tree, "-m", &message];
+        if let Some(head) = &head {
+            args.extend(["-p", head]);
+        }
+        let commit = git::run_in(&dest, args)?;
+        // The empty old value makes update-ref refuse to overwrite an archive.
+        for attempt in 0.. {
+            let reference = match attempt {
+                0 => format!("{}/{}/{}", ARCHIVE_REFS, name, stamp),
+                _ => format!("{}/{}/{}-{}", ARCHIVE_REFS, name, stamp, attempt),
+            };
+            match git::run_in(&self.git_cwd, ["update-ref", &reference, &commit, ""]) {
+                Ok(_) => {
+                    println!("archived worktree '{}' to {}", name, reference);
+                    return Ok(());
+                }
+                Err(_) if self.ref_exists(&reference) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        unreachable!()
+    }
+
+    fn ref_exists(&self, reference: &str) -> bool {
+        git::run_in(
+            &self.git_cwd,
+            ["show-ref", "--verify", "--quiet", reference],
+        )
+        .is_ok()
+    }
+
+    /// Accepts a full ref, `<name>/<timestamp>`, or just `<name>` for its latest
+    /// archive. Names must match exactly; glob characters are refused so a
+    /// pattern can't pick some other worktree's archive.
+    fn resolve_archive(&self, archive: &str) -> Result<String> {
+        if archive.contains(['*', '?', '[', '\\']) {
+            bail!("no archive matching '{}'", archive);
+        }
+        let reference = if archive.starts_with("refs/") {
+            archive.to_string()
+        } else {
+            format!("{}/{}", ARCHIVE_REFS, archive)
+        };
+        if self.ref_exists(&reference) {
+            return Ok(reference);
+        }
+        let latest = git::run_in(
+            &self.git_cwd,
+            [
+                "for-each-ref",
+                "--sort=-version:refname",
+                "--count=1",
+                "--format=%(refname)",
+                &reference,
+            ],
+        )?;
+        if latest.is_empty() {
+            bail!("no archive matching '{}'", archive);
+        }
+        Ok(latest)
+    }
+
+    /// Prunes stale worktree metadata and drops the git dirs that only exist
+    /// for linked worktrees once they're empty.
+    fn remove_empty_git_dirs(&self) -> Result<()> {
+        let prune = [OsString::from("worktree"), OsString::from("prune")];
+        if !self.skip(format_args!("run: {}", command_line(&self.git_cwd, &prune))) {
             process::Command::new("git")
-                .arg("worktree")
-                .arg("prune")
-                .current_dir(&self.root)
+                .args(&prune)
+                .current_dir(&self.git_cwd)
                 .status()
                 .ok();
+        }
+        self.remove_dir_if_empty(&self.git_common_dir.join("worktrees"))?;
+        self.remove_dir_if_empty(&self.git_common_dir.join("refs/worktree"))?;
+        self.remove_dir_if_empty(&self.git_common_dir.join("logs/refs/worktree"))?;
+        self.remove_dir_if_empty(self.metadata().dir())?;
+        Ok(())
+    }
 
-            remove_dir_if_empty(&self.git_common_dir.join("worktrees"))?;
-            remove_dir_if_empty(&self.git_common_dir.join("refs/worktree"))?;
-            remove_dir_if_empty(&self.git_common_dir.join("logs/refs/worktree"))?;
+    fn remove_dir_if_empty(&self, path: &Path) -> Result<()> {
+        if !path.is_dir() || self.skip(format_args!("remove {} if empty", path.display())) {
+            return Ok(());
         }
+        remove_dir_if_empty(path)
+    }
 
-        run_shell(&self.root)?;
-        Ok(())
+    /// In dry-run mode, reports `action` and returns `true` so the caller skips it.
+    fn skip(&self, action: fmt::Arguments) -> bool {
+        if self.dry_run {
+            println!("would {}", action);
+        }
+        self.dry_run
+    }
+
+    /// The exclude pattern for the worktrees directory, or `None` when it lives
+    /// outside the main checkout and can't show up in `git status`.
+    fn exclude_entry(&self) -> Result<Option<String>> {
+        if self.bare {
+            return Ok(None);
+        }
+        let Ok(relative) = self.worktrees_dir.strip_prefix(&self.root) else {
+            return Ok(None);
+        };
+        let relative = relative
+            .to_str()
+            .context("worktrees directory is not valid UTF-8")?;
+        if relative.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(format!("/{}/", relative.replace('\\', "/"))))
+    }
+
+    fn is_ignored(&self, entry: &str) -> Result<bool> {
+        let status = process::Command::new("git")
+            .arg("check-ignore")
+            .arg("-q")
+            .arg(entry.trim_start_matches('/'))
+            .current_dir(&self.root)
+            .status()
+            .context("failed to run git check-ignore")?;
+        match status.code() {
+            Some(0) => Ok(true),
+            Some(1) => Ok(false),
+            _ => bail!(Error::Git(format!("git check-ignore failed for {}", entry))),
+        }
+    }
+
+    fn ensure_worktrees_dir_excluded(&self) -> Result<()> {
+        let Some(entry) = self.exclude_entry()? else {
+            return Ok(());
+        };
+        if self.is_ignored(&entry)? {
+            return Ok(());
+        }
+        let exclude = self.git_common_dir.join("info/exclude");
+        if self.skip(format_args!("append {} to {}", entry, exclude.display())) {
+            return Ok(());
+        }
+        if let Some(parent) = exclude.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let mut contents = fs::read_to_string(&exclude).unwrap_or_default();
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push_str(&entry);
+        contents.push('\n');
+        fs::write(&exclude, contents)
+            .with_context(|| format!("failed to write {}", exclude.display()))
+    }
+
+    /// The commit new worktrees start detached at, or `None` on an unborn HEAD.
+    fn start_point(&self) -> Option<String> {
+        git::run_in(&self.git_cwd, ["rev-parse", "--verify", "--quiet", "HEAD"]).ok()
+    }
+
+    /// An unborn HEAD has nothing to detach at, so start the worktree on a new
+    /// orphan branch named after it instead (git 2.42+).
+    fn add_orphan_worktree(&self, dest: &Path) -> Result<()> {
+        if !git::supports_orphan_worktrees() {
+            bail!(
+                "this repository has no commits yet, and this git is too old to create an \
+                 orphan worktree (needs git 2.42 or newer); make an initial commit first, \
+                 e.g. `git commit --allow-empty -m init`"
+            );
+        }
+        let args = worktree_add_args("--orphan", dest);
+        if self.skip(format_args!("run: {}", command_line(&self.git_cwd, &args))) {
+            return Ok(());
+        }
+        git_worktree_add_with_retry(&self.git_cwd, &args)
+    }
+
+    /// Records under the repo lock so concurrent switches don't drop entries.
+    fn record_history(&self, name: &str) -> Result<()> {
+        let _lock = self.lock()?;
+        History::new(&self.git_common_dir).record(name)
+    }
+
+    fn metadata(&self) -> MetadataStore {
+        MetadataStore::new(&self.git_common_dir)
+    }
+
+    /// Gives a worktree that came back or was brought in (restore, adopt,
+    /// trash restore) a metadata record, keeping whatever one it already has.
+    fn ensure_metadata(&self, name: &str, base: Option<String>) -> Result<()> {
+        let store = self.metadata();
+        let mut metadata = store.load(name)?.unwrap_or_default();
+        if metadata.created_at.is_none() {
+            metadata.created_at = Some(history::now());
+            metadata.created_by = self.user_name();
+        }
+        metadata.base = metadata.base.or(base);
+        store.save(name, &metadata)
+    }
+
+    /// Who to record as a worktree's creator: git's `user.name`, else `$USER`.
+    fn user_name(&self) -> Option<String> {
+        git::run_in(&self.git_cwd, ["config", "user.name"])
+            .ok()
+            .filter(|name| !name.is_empty())
+            .or_else(|| env::var("USER").ok())
+    }
+
+    /// Takes the repo lock. Every mutating command goes through here, so it is
+    /// also where the checkout root is remembered for separate git dirs;
+    /// read-only commands and dry runs never write into the git dir.
+    fn lock(&self) -> Result<Option<RepoLock>> {
+        if self.dry_run {
+            return Ok(None);
+        }
+        let lock = RepoLock::acquire(
+            &self.git_common_dir.join("worktree-tool.lock"),
+            self.lock_timeout,
+        )?;
+        if self.separate_git_dir {
+            let recorded = self.git_common_dir.join(ROOT_FILE);
+            if fs::read_to_string(&recorded).ok().as_deref().map(str::trim) != self.root.to_str() {
+                fs::write(&recorded, format!("{}\n", self.root.display()))
+                    .with_context(|| format!("failed to write {}", recorded.display()))?;
+            }
+        }
+        Ok(Some(lock))
+    }
+
+    fn locked_worktrees(&self) -> Result<HashMap<String, String>> {
+        let mut locked = HashMap::new();
+        for worktree in git::worktree_list(&self.git_cwd)? {
+            if worktree.path.parent() != Some(self.worktrees_dir.as_path()) {
+                continue;
+            }
+            if let (Some(reason), Some(name)) = (
+                worktree.locked,
+                worktree.path.file_name().and_then(|name| name.to_str()),
+            ) {
+                locked.insert(name.to_string(), reason);
+            }
+        }
+        Ok(locked)
+    }
+
+    /// Checkouts in the worktrees directory that this repo's `git worktree
+    /// list` knows about. The directory may be shared with other repos, so
+    /// anything else in it is not ours to list or touch.
+    fn worktree_names(&self) -> Result<Vec<String>> {
+        if !self.worktrees_dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut names: Vec<_> = git::worktree_list(&self.git_cwd)?
+            .into_iter()
+            .filter(|worktree| {
+                worktree.path.is_dir()
+                    && worktree
+                        .path
+                        .parent()
+                        .is_some_and(|parent| same_path(parent, &self.worktrees_dir))
+            })
+            .filter_map(|worktree| worktree.path.file_name()?.to_str().map(String::from))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Whether `path` is a checkout of this repo even if its links are broken:
+    /// its `.git` file names one of our admin dirs, and that admin dir's
+    /// back-link names a directory called like `path`.
+    fn is_own_checkout(&self, path: &Path) -> bool {
+        let Ok(contents) = fs::read_to_string(path.join(".git")) else {
+            return false;
+        };
+        let Some(id) = contents
+            .trim()
+            .strip_prefix("gitdir: ")
+            .and_then(|gitdir| Path::new(gitdir).file_name())
+        else {
+            return false;
+        };
+        let admin = self.git_common_dir.join("worktrees").join(id);
+        let Ok(backlink) = fs::read_to_string(admin.join("gitdir")) else {
+            return false;
+        };
+        Path::new(backlink.trim())
+            .parent()
+            .and_then(Path::file_name)
+            == path.file_name()
+    }
+
+    fn previous_worktree(&self) -> Result<String> {
+        let current = self.current_worktree();
+        let names = self.worktree_names()?;
+        History::new(&self.git_common_dir)
+            .entries()?
+            .into_iter()
+            .map(|entry| entry.name)
+            .find(|name| current.as_ref() != Some(name) && names.contains(name))
+            .context("no previous worktree to switch to")
+    }
+
+    fn current_worktree(&self) -> Option<String> {
+        let cwd = env::current_dir().ok()?.canonicalize().ok()?;
+        let worktrees_dir = self.worktrees_dir.canonicalize().ok()?;
+        let relative = cwd.strip_prefix(worktrees_dir).ok()?;
+        relative
+            .components()
+            .next()?
+            .as_os_str()
+            .to_str()
+            .map(String::from)
+    }
+
+    /// Resolves a name for commands that destroy or rewrite a worktree: only
+    /// an exact name or a unique numeric shorthand (`3` → `3-wt`), never a
+    /// prefix or fuzzy match that could pick the wrong one.
+    fn resolve_exact_name(&self, query: &str) -> Result<String> {
+        validate_worktree_name(query)?;
+        let names = self.worktree_names()?;
+        exact_or_indexed(query, &names)
+            .ok_or_else(|| Error::WorktreeMissing(query.to_string()).into())
+    }
+
+    fn resolve_worktree_name(&self, query: &str) -> Result<String> {
+        validate_worktree_name(query)?;
+        let names = self.worktree_names()?;
+        if let Some(name) = exact_or_indexed(query, &names) {
+            return Ok(name);
+        }
+        let prefixed: Vec<_> = names
+            .iter()
+            .filter(|name| name.starts_with(query))
+            .collect();
+        let candidates = if prefixed.is_empty() {
+            names
+                .iter()
+                .filter(|name| fuzzy_match(query, name))
+                .collect()
+        } else {
+            prefixed
+        };
+        match candidates[..] {
+            [] => bail!(Error::WorktreeMissing(query.to_string())),
+            [name] => Ok(name.clone()),
+            _ => bail!(Error::AmbiguousName {
+                query: query.to_string(),
+                candidates: candidates.into_iter().cloned().collect(),
+            }),
+        }
     }
 
     fn enter_worktree(&self, dest: &Path, command: Option<CommandSpec>) -> Result<()> {
+        // History only feeds `switch -` and `recent`; never let it block entering.
+        if let Some(name) = dest.file_name().and_then(|name| name.to_str())
+            && let Err(err) = self.record_history(name)
+        {
+            eprintln!("warning: failed to record history: {:#}", err);
+        }
         env::set_current_dir(dest)?;
         println!("{}", dest.display());
         if let Some(command) = command {
-            let status = process::Command::new(&command.program)
-                .current_dir(dest)
-                .args(command.args)
+            let status = command_in(dest, &command)
                 .status()
                 .with_context(|| format!("failed to run {}", command.program))?;
             if !status.success() {
@@ -156,6 +1606,298 @@ impl Repo {
     }
 }
 
+enum ExecOutcome {
+    Passed,
+    Failed(Option<i32>),
+    Error(String),
+}
+
+impl std::fmt::Display for ExecOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Passed => write!(f, "ok"),
+            Self::Failed(Some(code)) => write!(f, "failed (exit {})", code),
+            Self::Failed(None) => write!(f, "failed (killed by signal)"),
+            Self::Error(err) => write!(f, "error: {}", err),
+        }
+    }
+}
+
+fn command_in(dest: &Path, command: &CommandSpec) -> process::Command {
+    let mut child = process::Command::new(&command.program);
+    child.current_dir(dest).args(&command.args);
+    child
+}
+
+fn run_inherited(dest: &Path, command: &CommandSpec) -> ExecOutcome {
+    match command_in(dest, command).status() {
+        Ok(status) if status.success() => ExecOutcome::Passed,
+        Ok(status) => ExecOutcome::Failed(status.code()),
+        Err(err) => ExecOutcome::Error(format!("failed to run {}: {}", command.program, err)),
+    }
+}
+
+fn run_buffered(name: &str, dest: &Path, command: &CommandSpec) -> ExecOutcome {
+    let output = match command_in(dest, command).output() {
+        Ok(output) => output,
+        Err(err) => {
+            return ExecOutcome::Error(format!("failed to run {}: {}", command.program, err));
+        }
+    };
+    {
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "==> {} <==", name).ok();
+        stdout.write_all(&output.stdout).ok();
+        io::stderr().lock().write_all(&output.stderr).ok();
+    }
+    if output.status.success() {
+        ExecOutcome::Passed
+    } else {
+        ExecOutcome::Failed(output.status.code())
+    }
+}
+
+fn pick_worktree(names: Vec<String>) -> Result<String> {
+    if !io::stdin().is_terminal() {
+        bail!("no worktree name given");
+    }
+    if names.is_empty() {
+        bail!("no worktrees to switch to");
+    }
+    let mut stderr = io::stderr().lock();
+    for (index, name) in names.iter().enumerate() {
+        writeln!(stderr, "{:>3}) {}", index + 1, name)?;
+    }
+    write!(stderr, "select a worktree [1-{}]: ", names.len())?;
+    stderr.flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    match answer.parse::<usize>() {
+        Ok(choice) if (1..=names.len()).contains(&choice) => Ok(names[choice - 1].clone()),
+        _ if names.iter().any(|name| name == answer) => Ok(answer.to_string()),
+        _ => bail!("invalid selection '{}'", answer),
+    }
+}
+
+fn fuzzy_match(query: &str, name: &str) -> bool {
+    let mut chars = name.chars();
+    query.chars().all(|wanted| chars.any(|c| c == wanted))
+}
+
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// `query` itself when it names a worktree, or the single worktree whose
+/// numeric prefix it matches.
+fn exact_or_indexed(query: &str, names: &[String]) -> Option<String> {
+    if names.iter().any(|name| name == query) {
+        return Some(query.to_string());
+    }
+    let index = query.parse::<usize>().ok()?;
+    let numbered: Vec<_> = names
+        .iter()
+        .filter(|name| worktree_index(name) == Some(index))
+        .collect();
+    match numbered[..] {
+        [name] => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// Works out the directory that owns `.worktrees` and whether the repo is bare.
+///
+/// - Bare repos (`project.git/`, `project/.bare`, `project/.git`) use the
+///   directory containing the git dir, so worktrees sit beside it.
+/// - Regular repos use the main worktree from `git worktree list`.
+/// - When the git dir lives apart from the checkout (`--separate-git-dir`,
+///   `GIT_DIR`), git reports the git dir as the main worktree, so the checkout
+///   comes from `core.worktree` or `--show-toplevel` in the main worktree, or
+///   from the path mutating commands remembered for linked worktrees that
+///   can't see it. The last value says whether that applies.
+fn discover_root(git_common_dir: &Path) -> Result<Option<(PathBuf, bool, bool)>> {
+    let main = git::worktree_list(&env::current_dir()?)?.into_iter().next();
+    let bare = main.as_ref().is_some_and(|main| main.bare)
+        || git::stdout(["rev-parse", "--is-bare-repository"]).is_ok_and(|out| out == "true");
+    if bare {
+        return Ok(git_common_dir
+            .parent()
+            .map(|root| (root.to_path_buf(), true, false)));
+    }
+    if let Some(main) = main
+        && !same_path(&main.path, git_common_dir)
+    {
+        return Ok(Some((main.path, false, false)));
+    }
+
+    let git_dir = git::stdout(["rev-parse", "--path-format=absolute", "--git-dir"])?;
+    let root = if let Ok(worktree) = git::stdout(["config", "core.worktree"]) {
+        Some(git_common_dir.join(worktree))
+    } else if same_path(Path::new(&git_dir), git_common_dir) {
+        git::stdout(["rev-parse", "--show-toplevel"])
+            .ok()
+            .map(PathBuf::from)
+    } else {
+        fs::read_to_string(git_common_dir.join(ROOT_FILE))
+            .ok()
+            .map(|root| PathBuf::from(root.trim()))
+    };
+    let Some(root) = root else {
+        return Ok(git_common_dir
+            .parent()
+            .map(|root| (root.to_path_buf(), false, false)));
+    };
+    let root = root.canonicalize().unwrap_or(root);
+    Ok(Some((root, false, true)))
+}
+
+/// The repository's name: the root directory's name, except for bare repos
+/// whose root holds the git dir, where it comes from the git dir itself
+/// (`project.git` → `project`; `project/.bare` → `project`). Several bare
+/// repos can share one directory, so the root alone doesn't identify them.
+fn repo_name(root: &Path, git_common_dir: &Path, bare: bool) -> String {
+    let dir_name = |path: &Path| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    if !bare {
+        return dir_name(root);
+    }
+    let git_dir = dir_name(git_common_dir);
+    match git_dir.as_str() {
+        ".bare" | ".git" => dir_name(root),
+        _ => git_dir
+            .strip_suffix(".git")
+            .filter(|stem| !stem.is_empty())
+            .unwrap_or(&git_dir)
+            .to_string(),
+    }
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Total size of the files under `path`, not following symlinks.
+fn disk_usage(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(|res| res.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => disk_usage(&entry.path()),
+            Ok(file_type) if file_type.is_file() => entry.metadata().map_or(0, |meta| meta.len()),
+            _ => 0,
+        })
+        .sum()
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// `<pid> <command>` for every process whose working directory is inside
+/// `path`, or `None` where that can't be determined.
+#[cfg(target_os = "linux")]
+fn processes_in(path: &Path) -> Option<Vec<String>> {
+    let path = path.canonicalize().ok()?;
+    let own = process::id();
+    let mut processes: Vec<(u32, String)> = fs::read_dir("/proc")
+        .ok()?
+        .filter_map(|res| res.ok())
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            let cwd = fs::read_link(entry.path().join("cwd")).ok()?;
+            if pid == own || !cwd.starts_with(&path) {
+                return None;
+            }
+            let command = fs::read_to_string(entry.path().join("comm")).unwrap_or_default();
+            Some((pid, command.trim().to_string()))
+        })
+        .collect();
+    processes.sort();
+    Some(
+        processes
+            .into_iter()
+            .map(|(pid, command)| format!("{} {}", pid, command))
+            .collect(),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn processes_in(_path: &Path) -> Option<Vec<String>> {
+    None
+}
+
+/// Describes what is wrong with the link between a worktree's `.git` file and
+/// its admin directory in the common dir, or `None` if both point at each other.
+fn worktree_link_problem(worktree: &Path) -> Option<String> {
+    let dot_git = worktree.join(".git");
+    let Ok(contents) = fs::read_to_string(&dot_git) else {
+        return Some("no .git file".to_string());
+    };
+    let Some(gitdir) = contents.trim().strip_prefix("gitdir: ") else {
+        return Some(format!("{} is malformed", dot_git.display()));
+    };
+    let admin = worktree.join(gitdir);
+    if !admin.is_dir() {
+        return Some(format!(".git points to missing {}", admin.display()));
+    }
+    let backlink = admin.join("gitdir");
+    let Ok(linked) = fs::read_to_string(&backlink) else {
+        return Some(format!("{} is missing", backlink.display()));
+    };
+    let linked = admin.join(linked.trim());
+    if !same_path(&linked, &dot_git) {
+        return Some(format!(
+            "{} points to {}",
+            backlink.display(),
+            linked.display()
+        ));
+    }
+    None
+}
+
 fn remove_dir_if_empty(path: &Path) -> Result<()> {
     if !path.is_dir() {
         return Ok(());
@@ -190,13 +1932,13 @@ fn next_worktree_name(worktree_root: &Path) -> Result<String> {
             if !entry.file_type()?.is_dir() {
                 continue;
             }
-            if let Some(name) = entry.file_name().to_str() {
-                if let Some(index) = worktree_index(name) {
-                    highest = Some(match highest {
-                        Some(current) if current > index => current,
-                        _ => index,
-                    });
-                }
+            if let Some(name) = entry.file_name().to_str()
+                && let Some(index) = worktree_index(name)
+            {
+                highest = Some(match highest {
+                    Some(current) if current > index => current,
+                    _ => index,
+                });
             }
         }
     }
@@ -213,28 +1955,74 @@ fn worktree_index(name: &str) -> Option<usize> {
 fn validate_worktree_name(name: &str) -> Result<()> {
     let mut components = Path::new(name).components();
     let Some(component) = components.next() else {
-        bail!("invalid worktree name '{}'", name);
+        bail!(Error::InvalidName(name.to_string()));
     };
     if components.next().is_some() {
-        bail!("invalid worktree name '{}'", name);
+        bail!(Error::InvalidName(name.to_string()));
     }
     match component {
         Component::Normal(_) => Ok(()),
-        _ => bail!("invalid worktree name '{}'", name),
+        _ => bail!(Error::InvalidName(name.to_string())),
     }
 }
 
-fn git_worktree_add_with_retry(root: &Path, dest: &Path) -> Result<()> {
+fn worktree_add_args(mode: &str, dest: &Path) -> Vec<OsString> {
+    vec![
+        "worktree".into(),
+        "add".into(),
+        mode.into(),
+        dest.as_os_str().to_os_string(),
+    ]
+}
+
+fn worktree_remove_args(worktree: &Path, forces: usize) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec!["worktree".into(), "remove".into()];
+    args.extend(iter::repeat_n(OsString::from("--force"), forces));
+    args.push(worktree.as_os_str().to_os_string());
+    args
+}
+
+/// Renders a git invocation the way a user could paste it into a shell.
+fn command_line(cwd: &Path, args: &[OsString]) -> String {
+    let cwd = cwd.as_os_str().to_os_string();
+    shell_words(
+        [OsString::from("git"), OsString::from("-C"), cwd]
+            .iter()
+            .chain(args),
+    )
+}
+
+fn shell_words<I, S>(words: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    words
+        .into_iter()
+        .map(|word| {
+            let word = word.as_ref().to_string_lossy();
+            let plain = !word.is_empty()
+                && word
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_./=:@+,".contains(c));
+            if plain {
+                word.into_owned()
+            } else {
+                format!("'{}'", word.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn git_worktree_add_with_retry(root: &Path, args: &[OsString]) -> Result<()> {
     let start = Instant::now();
     let mut delay = Duration::from_millis(30);
     let deadline = Duration::from_secs(3);
 
     loop {
         let output = process::Command::new("git")
-            .arg("worktree")
-            .arg("add")
-            .arg("--detach")
-            .arg(dest)
+            .args(args)
             .current_dir(root)
             .output()
             .context("failed to call git worktree add")?;
@@ -250,23 +2038,20 @@ fn git_worktree_add_with_retry(root: &Path, dest: &Path) -> Result<()> {
         }
         let stderr = stderr.trim();
         if stderr.is_empty() {
-            bail!("git worktree add failed");
+            bail!(Error::Git("git worktree add failed".to_string()));
         }
-        bail!("git worktree add failed: {}", stderr);
+        bail!(Error::Git(format!("git worktree add failed: {}", stderr)));
     }
 }
 
-fn git_worktree_remove_with_retry(root: &Path, worktree: &Path) -> Result<()> {
+fn git_worktree_remove_with_retry(root: &Path, worktree: &Path, args: &[OsString]) -> Result<()> {
     let start = Instant::now();
     let mut delay = Duration::from_millis(30);
     let deadline = Duration::from_secs(3);
 
     loop {
         let output = process::Command::new("git")
-            .arg("worktree")
-            .arg("remove")
-            .arg("--force")
-            .arg(worktree)
+            .args(args)
             .current_dir(root)
             .output()
             .context("failed to call git worktree remove")?;
@@ -282,13 +2067,16 @@ fn git_worktree_remove_with_retry(root: &Path, worktree: &Path) -> Result<()> {
         }
         let stderr = stderr.trim();
         if stderr.is_empty() {
-            bail!("git worktree remove failed for {}", worktree.display());
+            bail!(Error::Git(format!(
+                "git worktree remove failed for {}",
+                worktree.display()
+            )));
         }
-        bail!(
+        bail!(Error::Git(format!(
             "git worktree remove failed for {}: {}",
             worktree.display(),
             stderr
-        );
+        )));
     }
 }
 
@@ -527,6 +527,21 @@ fn init_does_not_overwrite_existing_config() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn init_works_with_a_broken_config() -> TestResult {
+    let temp = TempDir::new()?;
+    fs::create_dir_all(temp.path().join(".worktree"))?;
+    fs::write(temp.path().join(".worktree/config.toml"), "trash = [\n")?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("init")
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("initialized config at"));
+    Ok(())
+}
+
 #[test]
 fn codex_create_runs_with_defaults() -> TestResult {
     let temp = TempDir::new()?;
@@ -874,3 +889,2048 @@ fn init_writes_default_config() -> TestResult {
     assert!(contents.contains("--dangerously-skip-permissions"));
     Ok(())
 }
+
+#[test]
+fn exec_runs_in_every_worktree_and_reports_failures() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    for name in ["a", "b", "c"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["create", name])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+            .assert()
+            .success();
+    }
+    fs::write(temp.path().join(".worktrees/b/fail"), "")?;
+
+    let output = Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["exec", "--", "sh", "-c", "pwd > ran; test ! -e fail"])
+        .output()?;
+    assert_eq!(output.status.code(), Some(11));
+    for name in ["a", "b", "c"] {
+        assert!(
+            temp.path()
+                .join(".worktrees")
+                .join(name)
+                .join("ran")
+                .exists()
+        );
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("==> a <=="));
+    assert!(stdout.lines().any(|line| line == "a  ok"));
+    assert!(stdout.lines().any(|line| line == "b  failed (exit 1)"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 of 3 worktrees failed"));
+    Ok(())
+}
+
+#[test]
+fn exec_parallel_with_filter_only_runs_matching_worktrees() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    for name in ["review-1", "review-2", "other"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["create", name])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+            .assert()
+            .success();
+    }
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["exec", "--parallel", "2", "--filter", "review-*"])
+        .args(["--", "sh", "-c", "echo hello; touch ran"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("==> review-1 <==\nhello\n"));
+    assert!(temp.path().join(".worktrees/review-1/ran").exists());
+    assert!(temp.path().join(".worktrees/review-2/ran").exists());
+    assert!(!temp.path().join(".worktrees/other/ran").exists());
+    Ok(())
+}
+
+#[test]
+fn switch_with_command_propagates_exit_code_and_does_not_start_shell() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["create", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+        .assert()
+        .success();
+
+    let shell_log = temp.path().join("shell.log");
+    let cmd_log = temp.path().join("cmd.log");
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args([
+            "switch",
+            "feature",
+            "sh",
+            "-c",
+            "pwd > \"$WT_CMD_LOG\"; exit 7",
+        ])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", &shell_log)
+        .env("WT_CMD_LOG", &cmd_log)
+        .assert()
+        .failure()
+        .code(7);
+
+    assert!(!shell_log.exists());
+    let recorded = fs::read_to_string(&cmd_log)?;
+    assert_eq!(
+        fs::canonicalize(recorded.trim())?,
+        fs::canonicalize(temp.path().join(".worktrees/feature"))?
+    );
+    Ok(())
+}
+
+#[test]
+fn switch_accepts_prefixes_and_numeric_shorthands() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    for name in ["0-wt", "review-api", "review-ui"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["create", name])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+            .assert()
+            .success();
+    }
+
+    for (query, expected) in [
+        ("0", "0-wt"),
+        ("review-a", "review-api"),
+        ("rvui", "review-ui"),
+    ] {
+        let log = temp.path().join("switch.log");
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["switch", query])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .env("WORKTREE_SHELL_LOG", &log)
+            .assert()
+            .success();
+        let recorded = fs::read_to_string(&log)?;
+        assert_eq!(
+            fs::canonicalize(recorded.trim())?,
+            fs::canonicalize(temp.path().join(".worktrees").join(expected))?
+        );
+    }
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["switch", "review"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "worktree name 'review' is ambiguous; candidates: review-api, review-ui",
+        ));
+    Ok(())
+}
+
+#[test]
+fn switch_without_name_requires_a_terminal() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("switch")
+        .write_stdin("")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no worktree name given"));
+    Ok(())
+}
+
+#[test]
+fn switch_dash_returns_to_previous_worktree() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    for name in ["one", "two"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["create", name])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+            .assert()
+            .success();
+    }
+
+    let log = temp.path().join("switch.log");
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path().join(".worktrees/two"))
+        .args(["switch", "-"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", &log)
+        .assert()
+        .success();
+    let recorded = fs::read_to_string(&log)?;
+    assert_eq!(
+        fs::canonicalize(recorded.trim())?,
+        fs::canonicalize(temp.path().join(".worktrees/one"))?
+    );
+
+    let output = Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("recent")
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let names: Vec<_> = stdout
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .collect();
+    assert_eq!(names, ["one", "two"]);
+    Ok(())
+}
+
+#[test]
+fn switch_dash_without_history_errors() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["switch", "-"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no previous worktree"));
+    Ok(())
+}
+
+#[test]
+fn clear_skips_locked_worktrees_unless_included() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    for name in ["keep", "drop"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["create", name])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["lock", "keep", "--reason", "long build"])
+        .assert()
+        .success();
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("keep  (locked: long build)"));
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("clear")
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "skipping locked worktree 'keep' (long build)",
+        ));
+    assert!(temp.path().join(".worktrees/keep").exists());
+    assert!(!temp.path().join(".worktrees/drop").exists());
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["clear", "--include-locked"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success();
+    assert!(!temp.path().join(".worktrees").exists());
+    assert!(!temp.path().join(".git/worktrees").exists());
+    Ok(())
+}
+
+#[test]
+fn unlock_makes_worktree_clearable_again() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["create", "one"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+        .assert()
+        .success();
+    for command in ["lock", "unlock"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args([command, "one"])
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("list")
+        .assert()
+        .success()
+        .stdout("one\n");
+    Ok(())
+}
+
+#[test]
+fn contended_lock_reports_holder_and_times_out() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+
+    let real_git = std::process::Command::new("sh")
+        .args(["-c", "command -v git"])
+        .output()?;
+    let real_git = String::from_utf8(real_git.stdout)?;
+    let bin = temp.path().join("bin");
+    fs::create_dir(&bin)?;
+    fs::write(
+        bin.join("git"),
+        format!(
+            r#"#!/bin/sh
+if [ "$1 $2" = "worktree add" ]; then sleep 3; fi
+exec {} "$@"
+"#,
+            real_git.trim()
+        ),
+    )?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(bin.join("git"), fs::Permissions::from_mode(0o755))?;
+    }
+    let path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_else(|_| String::from("/usr/bin"))
+    );
+
+    let mut holder = std::process::Command::new(assert_cmd::cargo::cargo_bin("worktree"))
+        .current_dir(temp.path())
+        .args(["create", "slow"])
+        .env("HOME", temp.path())
+        .env("PATH", &path)
+        .env("SHELL", &shell)
+        .stdout(std::process::Stdio::null())
+        .spawn()?;
+    std::thread::sleep(std::time::Duration::from_millis(1000));
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["--lock-timeout", "1", "create", "fast"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "waiting for lock held by pid {} (worktree create slow)",
+            holder.id()
+        )))
+        .stderr(predicate::str::contains("timed out after 1s"))
+        .code(8);
+
+    assert!(holder.wait()?.success());
+    assert!(temp.path().join(".worktrees/slow").exists());
+    assert!(!temp.path().join(".worktrees/fast").exists());
+    Ok(())
+}
+
+#[test]
+fn worktrees_dir_can_live_outside_the_repo() -> TestResult {
+    let temp = TempDir::new()?;
+    let repo = temp.path().join("project");
+    fs::create_dir(&repo)?;
+    init_repo(&repo)?;
+    let shell = fake_shell(temp.path())?;
+    fs::create_dir_all(temp.path().join(".worktree"))?;
+    fs::write(
+        temp.path().join(".worktree/config.toml"),
+        "worktrees_dir = \"~/scratch/{repo}\"\n",
+    )?;
+    let external = temp.path().join("scratch/project");
+
+    Command::cargo_bin("worktree")?
+        .current_dir(&repo)
+        .args(["create", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+        .assert()
+        .success();
+    assert!(external.join("feature").is_dir());
+    assert!(!repo.join(".worktrees").exists());
+
+    Command::cargo_bin("worktree")?
+        .current_dir(external.join("feature"))
+        .args(["create", "nested"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+        .assert()
+        .success();
+    assert!(external.join("nested").is_dir());
+
+    Command::cargo_bin("worktree")?
+        .current_dir(&repo)
+        .arg("list")
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout("feature\nnested\n");
+
+    let log = temp.path().join("switch.log");
+    Command::cargo_bin("worktree")?
+        .current_dir(&repo)
+        .args(["switch", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", &log)
+        .assert()
+        .success();
+    let recorded = fs::read_to_string(&log)?;
+    assert_eq!(
+        fs::canonicalize(recorded.trim())?,
+        fs::canonicalize(external.join("feature"))?
+    );
+
+    Command::cargo_bin("worktree")?
+        .current_dir(&repo)
+        .arg("clear")
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success();
+    assert!(!external.exists());
+    assert!(!repo.join(".git/worktrees").exists());
+    Ok(())
+}
+
+#[test]
+fn create_excludes_worktrees_dir_from_main_checkout() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("doctor")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("/.worktrees/ is not ignored"));
+
+    for name in ["one", "two"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["create", name])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+            .assert()
+            .success();
+    }
+    let exclude = fs::read_to_string(temp.path().join(".git/info/exclude"))?;
+    assert_eq!(
+        exclude
+            .lines()
+            .filter(|line| *line == "/.worktrees/")
+            .count(),
+        1
+    );
+
+    let status = std::process::Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(temp.path())
+        .output()?;
+    assert!(!String::from_utf8_lossy(&status.stdout).contains(".worktrees"));
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ok: /.worktrees/ is ignored"));
+    Ok(())
+}
+
+#[test]
+fn bare_repo_keeps_worktrees_beside_the_git_dir() -> TestResult {
+    let temp = TempDir::new()?;
+    let src = temp.path().join("src");
+    fs::create_dir(&src)?;
+    init_repo(&src)?;
+    git(temp.path(), ["clone", "--bare", "src", "plain/project.git"])?.success()?;
+    git(temp.path(), ["clone", "--bare", "src", "layout/.bare"])?.success()?;
+    let shell = fake_shell(temp.path())?;
+
+    for (dir, expected) in [
+        ("plain/project.git", "plain/.worktrees/feature"),
+        ("layout/.bare", "layout/.worktrees/feature"),
+    ] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path().join(dir))
+            .args(["create", "feature"])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+            .assert()
+            .success();
+        let feature = temp.path().join(expected);
+        assert!(feature.join("README.md").exists());
+
+        Command::cargo_bin("worktree")?
+            .current_dir(&feature)
+            .arg("list")
+            .env("HOME", temp.path())
+            .assert()
+            .success()
+            .stdout("feature\n");
+    }
+    assert!(!temp.path().join(".worktrees").exists());
+    assert!(!temp.path().join("plain/project.git/.worktrees").exists());
+    let exclude = fs::read_to_string(temp.path().join("plain/project.git/info/exclude"))?;
+    assert!(!exclude.contains("/.worktrees/"));
+    Ok(())
+}
+
+#[test]
+fn bare_repos_in_one_directory_get_their_own_repo_name() -> TestResult {
+    let temp = TempDir::new()?;
+    let src = temp.path().join("src");
+    fs::create_dir(&src)?;
+    init_repo(&src)?;
+    let shell = fake_shell(temp.path())?;
+    fs::create_dir_all(temp.path().join(".worktree"))?;
+    fs::write(
+        temp.path().join(".worktree/config.toml"),
+        "worktrees_dir = \"~/wts/{repo}\"\n",
+    )?;
+    for repo in ["project", "other"] {
+        git(
+            temp.path(),
+            ["clone", "--bare", "src", &format!("plain/{repo}.git")],
+        )?
+        .success()?;
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path().join(format!("plain/{repo}.git")))
+            .args(["create", "feature"])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .assert()
+            .success();
+        assert!(
+            temp.path()
+                .join(format!("wts/{repo}/feature/README.md"))
+                .exists()
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn separate_git_dir_uses_the_checkout_as_root() -> TestResult {
+    let temp = TempDir::new()?;
+    let work = temp.path().join("work");
+    git(
+        temp.path(),
+        ["init", "--separate-git-dir", "gitdir", "work"],
+    )?
+    .success()?;
+    fs::write(work.join("README.md"), "hi")?;
+    git(&work, ["add", "."])?.success()?;
+    git(&work, ["commit", "-m", "init"])?.success()?;
+    let shell = fake_shell(temp.path())?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(&work)
+        .arg("list")
+        .env("HOME", temp.path())
+        .assert()
+        .success();
+    assert!(!temp.path().join("gitdir/worktree-tool.root").exists());
+
+    Command::cargo_bin("worktree")?
+        .current_dir(&work)
+        .args(["create", "one"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+        .assert()
+        .success();
+    assert!(work.join(".worktrees/one/README.md").exists());
+
+    Command::cargo_bin("worktree")?
+        .current_dir(work.join(".worktrees/one"))
+        .args(["create", "two"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+        .assert()
+        .success();
+    assert!(work.join(".worktrees/two/README.md").exists());
+    assert!(!temp.path().join(".worktrees").exists());
+    assert!(!temp.path().join("gitdir/.worktrees").exists());
+    Ok(())
+}
+
+fn git_supports_orphan_worktrees() -> bool {
+    std::process::Command::new("git")
+        .args(["worktree", "add", "-h"])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("--orphan"))
+}
+
+#[test]
+fn create_in_repo_without_commits_starts_an_orphan_branch() -> TestResult {
+    if !git_supports_orphan_worktrees() {
+        return Ok(());
+    }
+    let temp = TempDir::new()?;
+    git(temp.path(), ["init"])?.success()?;
+    let shell = fake_shell(temp.path())?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["create", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+        .assert()
+        .success();
+    let feature = temp.path().join(".worktrees/feature");
+    assert!(feature.is_dir());
+    assert_eq!(worktrees(temp.path())?, vec![feature.clone()]);
+    let branch = std::process::Command::new("git")
+        .args(["symbolic-ref", "--short", "HEAD"])
+        .current_dir(&feature)
+        .output()?;
+    assert_eq!(String::from_utf8(branch.stdout)?.trim(), "feature");
+    Ok(())
+}
+
+#[test]
+fn create_in_repo_without_commits_explains_old_git() -> TestResult {
+    if git_supports_orphan_worktrees() {
+        return Ok(());
+    }
+    let temp = TempDir::new()?;
+    git(temp.path(), ["init"])?.success()?;
+    let shell = fake_shell(temp.path())?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["create", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "this repository has no commits yet, and this git is too old to create an orphan \
+             worktree (needs git 2.42 or newer); make an initial commit first, e.g. \
+             `git commit --allow-empty -m init`",
+        ));
+    assert!(!temp.path().join(".worktrees/feature").exists());
+    Ok(())
+}
+
+#[test]
+fn repo_option_and_env_var_select_the_repo() -> TestResult {
+    let temp = TempDir::new()?;
+    let repo = temp.path().join("project");
+    fs::create_dir(&repo)?;
+    init_repo(&repo)?;
+    let shell = fake_shell(temp.path())?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .env("WORKTREE_REPO", &repo)
+        .args(["create", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+        .assert()
+        .success();
+    assert!(repo.join(".worktrees/feature").is_dir());
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["-C", "project/.worktrees/feature", "list"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout("feature\n");
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["list", "-C", "missing"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot change to missing"));
+    Ok(())
+}
+
+#[test]
+fn strict_mode_fails_outside_a_repo() -> TestResult {
+    let temp = TempDir::new()?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["--strict", "list"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("not in a git repo"));
+    Ok(())
+}
+
+#[test]
+fn failures_have_distinct_exit_codes() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    for name in ["review-api", "review-ui"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["create", name])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+            .assert()
+            .success();
+    }
+
+    for (args, code) in [
+        (["create", "../oops"], 4),
+        (["create", "review-ui"], 5),
+        (["switch", "missing"], 6),
+        (["switch", "review"], 7),
+    ] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(args)
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .assert()
+            .code(code);
+    }
+
+    fs::create_dir_all(temp.path().join(".worktree"))?;
+    fs::write(temp.path().join(".worktree/config.toml"), "commands = 1\n")?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("list")
+        .env("HOME", temp.path())
+        .assert()
+        .code(10)
+        .stderr(predicate::str::contains("invalid config"));
+    Ok(())
+}
+
+#[test]
+fn remove_deletes_a_single_worktree() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    for name in ["one", "two"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["create", name])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+            .assert()
+            .success();
+    }
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["lock", "two"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["remove", "one"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("removed worktree 'one'"));
+    assert!(!temp.path().join(".worktrees/one").exists());
+    assert!(!temp.path().join(".git/worktrees/one").exists());
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["remove", "two"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("worktree 'two' is locked"));
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["remove", "--force", "two"])
+        .assert()
+        .success();
+    assert!(!temp.path().join(".worktrees").exists());
+    assert!(!temp.path().join(".git/worktrees").exists());
+    Ok(())
+}
+
+#[test]
+fn remove_only_accepts_exact_names_or_indexes() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    for name in ["review-api", "3-wt"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["create", name])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+            .assert()
+            .success();
+    }
+
+    for query in ["review", "rvwapi"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["remove", query])
+            .assert()
+            .code(6);
+    }
+    assert!(temp.path().join(".worktrees/review-api").is_dir());
+
+    fs::remove_file(temp.path().join(".git/worktree-tool.lock"))?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["--dry-run", "remove", "3"])
+        .assert()
+        .success();
+    assert!(!temp.path().join(".git/worktree-tool.lock").exists());
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["remove", "3"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("removed worktree '3-wt'"));
+    Ok(())
+}
+
+#[test]
+fn dry_run_prints_plan_without_changing_anything() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    let root = temp.path().display().to_string();
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["--dry-run", "create", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("dry.log"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "would run: git -C {root} worktree add --detach {root}/.worktrees/feature"
+        )))
+        .stdout(predicate::str::contains(format!(
+            "would append /.worktrees/ to {root}/.git/info/exclude"
+        )));
+    assert!(!temp.path().join(".worktrees").exists());
+    assert!(!temp.path().join("dry.log").exists());
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["create", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+        .assert()
+        .success();
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["clear", "--dry-run"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_CLEAR_LOG", temp.path().join("clear.log"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "would run: git -C {root} worktree remove --force {root}/.worktrees/feature"
+        )))
+        .stdout(predicate::str::contains(format!(
+            "would remove {root}/.worktrees if empty"
+        )))
+        .stdout(predicate::str::contains(format!(
+            "would run: git -C {root} worktree prune"
+        )))
+        .stdout(predicate::str::contains(format!(
+            "would remove {root}/.git/worktrees if empty"
+        )));
+    assert!(temp.path().join(".worktrees/feature").exists());
+    assert!(temp.path().join(".git/worktrees/feature").exists());
+    assert!(!temp.path().join("clear.log").exists());
+
+    let locked = || temp.path().join(".git/worktrees/feature/locked").exists();
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["--dry-run", "lock", "feature"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "would run: git -C {root} worktree lock {root}/.worktrees/feature\n"
+        ));
+    assert!(!locked());
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["lock", "feature"])
+        .assert()
+        .success();
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["--dry-run", "unlock", "feature"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "would run: git -C {root} worktree unlock {root}/.worktrees/feature\n"
+        ));
+    assert!(locked());
+    Ok(())
+}
+
+#[test]
+fn archive_and_restore_round_trip_local_state() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["create", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+        .assert()
+        .success();
+    let feature = temp.path().join(".worktrees/feature");
+    fs::write(feature.join("committed.txt"), "detached work")?;
+    git(&feature, ["add", "committed.txt"])?.success()?;
+    git(&feature, ["commit", "-m", "detached"])?.success()?;
+    fs::write(feature.join("README.md"), "changed")?;
+    fs::write(feature.join("untracked.txt"), "new")?;
+
+    let output = Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["archive", "feature"])
+        .env("GIT_AUTHOR_NAME", "Test")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "Test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("archived worktree 'feature' to refs/worktree-archive/feature/"));
+    // A second archive in the same second gets its own ref instead of failing.
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["archive", "feature"])
+        .env("GIT_AUTHOR_NAME", "Test")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "Test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .assert()
+        .success();
+    let refs = std::process::Command::new("git")
+        .args([
+            "for-each-ref",
+            "--format=%(refname)",
+            "refs/worktree-archive",
+        ])
+        .current_dir(temp.path())
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&refs.stdout).lines().count(), 2);
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["archive", "feat"])
+        .assert()
+        .code(6);
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["remove", "--force", "feature"])
+        .assert()
+        .success();
+    assert!(!feature.exists());
+    for archive in ["feat", "feat*"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["restore", archive])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(format!(
+                "no archive matching '{archive}'"
+            )));
+    }
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["restore", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("restore.log"))
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(feature.join("committed.txt"))?,
+        "detached work"
+    );
+    assert_eq!(fs::read_to_string(feature.join("README.md"))?, "changed");
+    assert_eq!(fs::read_to_string(feature.join("untracked.txt"))?, "new");
+    let status = std::process::Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(&feature)
+        .output()?;
+    let status = String::from_utf8_lossy(&status.stdout);
+    assert!(status.contains(" M README.md"));
+    assert!(status.contains("?? untracked.txt"));
+    Ok(())
+}
+
+#[test]
+fn archive_on_remove_snapshots_worktrees_before_clear() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    fs::create_dir_all(temp.path().join(".worktree"))?;
+    fs::write(
+        temp.path().join(".worktree/config.toml"),
+        "archive_on_remove = true\n",
+    )?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["create", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("WORKTREE_SHELL_LOG", temp.path().join("log"))
+        .assert()
+        .success();
+    fs::write(temp.path().join(".worktrees/feature/notes.txt"), "keep me")?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("clear")
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .env("GIT_AUTHOR_NAME", "Test")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "Test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .assert()
+        .success();
+    assert!(!temp.path().join(".worktrees").exists());
+
+    let refs = std::process::Command::new("git")
+        .args([
+            "for-each-ref",
+            "--format=%(refname)",
+            "refs/worktree-archive",
+        ])
+        .current_dir(temp.path())
+        .output()?;
+    let refs = String::from_utf8_lossy(&refs.stdout);
+    let archive = refs.lines().next().ok_or("no archive ref")?;
+    assert!(archive.starts_with("refs/worktree-archive/feature/"));
+    let contents = std::process::Command::new("git")
+        .args(["show", &format!("{archive}:notes.txt")])
+        .current_dir(temp.path())
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&contents.stdout), "keep me");
+    Ok(())
+}
+
+#[test]
+fn trash_keeps_cleared_worktrees_until_emptied() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    fs::create_dir_all(temp.path().join(".worktree"))?;
+    fs::write(temp.path().join(".worktree/config.toml"), "trash = true\n")?;
+    for name in ["feature", "other"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["create", name])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .assert()
+            .success();
+    }
+    fs::write(temp.path().join(".worktrees/feature/notes.txt"), "keep me")?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("clear")
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "moved worktree 'feature' to the trash",
+        ));
+    assert!(worktrees(temp.path())?.is_empty());
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["trash", "list"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("feature").and(predicate::str::contains("other")));
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["trash", "restore", "feature"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("restored worktree 'feature'"));
+    assert_eq!(
+        fs::read_to_string(temp.path().join(".worktrees/feature/notes.txt"))?,
+        "keep me"
+    );
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["trash", "empty"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("deleted trashed worktree 'other'"));
+    assert!(!temp.path().join(".git/worktree-trash").exists());
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["trash", "list"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+    Ok(())
+}
+
+#[test]
+fn trash_restores_locked_worktrees() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    fs::create_dir_all(temp.path().join(".worktree"))?;
+    fs::write(temp.path().join(".worktree/config.toml"), "trash = true\n")?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["create", "kept"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success();
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["lock", "kept", "--reason", "keep"])
+        .assert()
+        .success();
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["remove", "--force", "kept"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout("moved worktree 'kept' to the trash\n");
+
+    // Trashed worktrees stay registered with git but aren't foreign.
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["list", "--all"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+    let trashed = fs::read_dir(temp.path().join(".git/worktree-trash"))?
+        .next()
+        .ok_or("nothing in the trash")??
+        .path();
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("adopt")
+        .arg(&trashed)
+        .args(["--name", "stolen"])
+        .env("HOME", temp.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "is not a worktree of this repository",
+        ));
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["trash", "restore", "kept"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("restored worktree 'kept'"));
+    assert!(temp.path().join(".worktrees/kept/README.md").exists());
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("list")
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("kept").and(predicate::str::contains("keep")));
+    Ok(())
+}
+
+#[test]
+fn gc_prunes_stale_entries_and_leftover_dirs() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    for name in ["gone", "kept"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["create", name])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .assert()
+            .success();
+    }
+    fs::remove_dir_all(temp.path().join(".worktrees/gone"))?;
+    fs::create_dir_all(temp.path().join(".worktrees/empty"))?;
+    fs::create_dir_all(temp.path().join(".worktrees/notes"))?;
+    fs::write(temp.path().join(".worktrees/notes/todo.txt"), "mine")?;
+    let unlinked = temp.path().join(".worktrees/unlinked");
+    fs::create_dir_all(&unlinked)?;
+    fs::write(
+        unlinked.join(".git"),
+        "gitdir: /nowhere/worktrees/unlinked\n",
+    )?;
+    fs::write(unlinked.join("wip.txt"), "unsaved")?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("gc")
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("pruned stale entry for")
+                .and(predicate::str::contains("removed leftover directory"))
+                .and(predicate::str::contains("leaving unregistered directory"))
+                .and(predicate::str::contains(format!(
+                    "leaving unregistered checkout {}",
+                    unlinked.display()
+                )))
+                .and(predicate::str::contains("run `worktree repair`")),
+        );
+    assert_eq!(
+        worktrees(temp.path())?,
+        vec![
+            temp.path().join(".worktrees/kept"),
+            temp.path().join(".worktrees/notes"),
+            unlinked.clone(),
+        ]
+    );
+    assert_eq!(fs::read_to_string(unlinked.join("wip.txt"))?, "unsaved");
+    assert!(!temp.path().join(".git/worktrees/gone").exists());
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("gc")
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("nothing to clean up"));
+    Ok(())
+}
+
+#[test]
+fn clear_filters_select_which_worktrees_to_remove() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    for name in ["feature", "review-1", "review-2"] {
+        Command::cargo_bin("worktree")?
+            .current_dir(temp.path())
+            .args(["create", name])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .assert()
+            .success();
+    }
+    fs::write(temp.path().join(".worktrees/review-2/README.md"), "edited")?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["clear", "--older-than", "7d"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("no worktrees match"));
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["clear", "--older-than", "99999999999999999w"])
+        .env("HOME", temp.path())
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "age '99999999999999999w' is too large",
+        ));
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["clear", "--match", "review-*", "--clean-only", "--merged"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "removing 1 worktree(s):\n  review-1\n",
+        ));
+    assert_eq!(
+        worktrees(temp.path())?,
+        vec![
+            temp.path().join(".worktrees/feature"),
+            temp.path().join(".worktrees/review-2"),
+        ]
+    );
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["clear", "--merged", "no-such-branch"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown branch 'no-such-branch'"));
+    Ok(())
+}
+
+#[test]
+fn mv_renames_worktree_and_keeps_history() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("create")
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success();
+    git(
+        &temp.path().join(".worktrees/0-wt"),
+        ["switch", "-c", "0-wt"],
+    )?
+    .success()?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["mv", "0-", "login-fix"])
+        .env("HOME", temp.path())
+        .assert()
+        .code(6);
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["mv", "0", "login-fix"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("renamed branch '0-wt' to 'login-fix'").and(
+                predicate::str::contains("moved worktree '0-wt' to 'login-fix'"),
+            ),
+        );
+    assert_eq!(
+        worktrees(temp.path())?,
+        vec![temp.path().join(".worktrees/login-fix")]
+    );
+    let branch = std::process::Command::new("git")
+        .args(["branch", "--show-current"])
+        .current_dir(temp.path().join(".worktrees/login-fix"))
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&branch.stdout).trim(), "login-fix");
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("recent")
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("login-fix").and(predicate::str::contains("0-wt").not()));
+
+    let login_fix = temp.path().join(".worktrees/login-fix");
+    git(&login_fix, ["switch", "-c", "topic"])?.success()?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["mv", "login-fix", "auth"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("renamed branch").not());
+    let branch = std::process::Command::new("git")
+        .args(["branch", "--show-current"])
+        .current_dir(temp.path().join(".worktrees/auth"))
+        .output()?;
+    assert_eq!(String::from_utf8_lossy(&branch.stdout).trim(), "topic");
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["mv", "auth", "../escape"])
+        .env("HOME", temp.path())
+        .assert()
+        .code(4);
+    Ok(())
+}
+
+#[test]
+fn adopt_moves_foreign_worktree_into_managed_dir() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    git(temp.path(), ["worktree", "add", "--detach", "foreign"])?.success()?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["list", "--all"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("foreign  (foreign)"));
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["adopt", "foreign", "--name", "adopted"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("adopted worktree 'adopted'"));
+    assert!(!temp.path().join("foreign").exists());
+    assert_eq!(
+        worktrees(temp.path())?,
+        vec![temp.path().join(".worktrees/adopted")]
+    );
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["list", "--all"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout("adopted\n");
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["adopt", "."])
+        .env("HOME", temp.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "is not a worktree of this repository",
+        ));
+    Ok(())
+}
+
+#[test]
+fn repair_relinks_worktrees_after_repo_move() -> TestResult {
+    let temp = TempDir::new()?;
+    let repo = temp.path().join("repo");
+    fs::create_dir_all(&repo)?;
+    init_repo(&repo)?;
+    let shell = fake_shell(temp.path())?;
+    Command::cargo_bin("worktree")?
+        .current_dir(&repo)
+        .args(["create", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success();
+
+    let moved = temp.path().join("moved");
+    fs::rename(&repo, &moved)?;
+    assert!(
+        git(&moved.join(".worktrees/feature"), ["status"])?
+            .success()
+            .is_err()
+    );
+
+    Command::cargo_bin("worktree")?
+        .current_dir(&moved)
+        .arg("repair")
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("repaired 'feature'"));
+    git(&moved.join(".worktrees/feature"), ["status"])?.success()?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(&moved)
+        .arg("repair")
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "all 1 worktree(s) are linked correctly",
+        ));
+    Ok(())
+}
+
+#[test]
+fn metadata_follows_worktree_lifecycle() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["create", "--description", "fix the login page", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success();
+    let meta = temp.path().join(".git/worktree-meta/feature.toml");
+    let contents = fs::read_to_string(&meta)?;
+    assert!(contents.contains("version = 1"));
+    assert!(contents.contains("description = \"fix the login page\""));
+    assert!(contents.contains("base = "));
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .arg("list")
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout("feature  fix the login page\n");
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["mv", "feature", "login"])
+        .env("HOME", temp.path())
+        .assert()
+        .success();
+    assert!(!meta.exists());
+    assert!(temp.path().join(".git/worktree-meta/login.toml").exists());
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["remove", "login"])
+        .env("HOME", temp.path())
+        .assert()
+        .success();
+    assert!(!temp.path().join(".git/worktree-meta").exists());
+    Ok(())
+}
+
+#[test]
+fn metadata_is_written_for_worktrees_brought_back_or_adopted() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    fs::create_dir_all(temp.path().join(".worktree"))?;
+    fs::write(temp.path().join(".worktree/config.toml"), "trash = true\n")?;
+    let meta = |name: &str| temp.path().join(format!(".git/worktree-meta/{name}.toml"));
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["create", "plain", "true"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success();
+    let contents = fs::read_to_string(meta("plain"))?;
+    assert!(contents.contains("created_at = "));
+    assert!(!contents.contains("tool = "));
+
+    git(temp.path(), ["worktree", "add", "--detach", "foreign"])?.success()?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["adopt", "foreign"])
+        .env("HOME", temp.path())
+        .assert()
+        .success();
+    assert!(fs::read_to_string(meta("foreign"))?.contains("base = "));
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["remove", "foreign"])
+        .env("HOME", temp.path())
+        .assert()
+        .success();
+    fs::remove_file(meta("foreign"))?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["trash", "restore", "foreign"])
+        .env("HOME", temp.path())
+        .assert()
+        .success();
+    assert!(fs::read_to_string(meta("foreign"))?.contains("created_at = "));
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["archive", "plain"])
+        .env("GIT_AUTHOR_NAME", "Test")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "Test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .assert()
+        .success();
+    fs::write(temp.path().join(".worktree/config.toml"), "")?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["remove", "plain"])
+        .env("HOME", temp.path())
+        .assert()
+        .success();
+    assert!(!meta("plain").exists());
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["restore", "plain"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success();
+    assert!(fs::read_to_string(meta("plain"))?.contains("created_at = "));
+    Ok(())
+}
+
+#[test]
+fn info_describes_a_worktree() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["create", "--description", "try things", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success();
+    let worktree = temp.path().join(".worktrees/feature");
+    fs::write(worktree.join("README.md"), "changed")?;
+    fs::write(worktree.join("new.txt"), "new")?;
+    git(&worktree, ["commit", "-am", "change", "--quiet"])?.success()?;
+    fs::write(worktree.join("README.md"), "changed again")?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["info", "feat"])
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("name:       feature")
+                .and(predicate::str::contains("about:      try things"))
+                .and(
+                    predicate::str::contains("HEAD:       ")
+                        .and(predicate::str::contains(" change\n")),
+                )
+                .and(predicate::str::contains("(1 ahead, 0 behind)"))
+                .and(predicate::str::contains(
+                    "changes:    0 staged, 1 modified, 1 untracked",
+                ))
+                .and(predicate::str::contains("locked:     no"))
+                .and(predicate::str::contains("entered:    just now")),
+        );
+    Ok(())
+}
+
+#[test]
+fn resume_relaunches_recorded_tool() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let bin = temp.path().join("bin");
+    fs::create_dir(&bin)?;
+    let log = temp.path().join("run.log");
+    fs::write(
+        bin.join("claude"),
+        r#"#!/bin/sh
+printf "%s\n" "$@" > "$WORKTREE_TEST_LOG"
+"#,
+    )?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(bin.join("claude"), fs::Permissions::from_mode(0o755))?;
+    }
+    let path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_else(|_| String::from("/usr/bin"))
+    );
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["claude", "create", "feature", "fix the bug"])
+        .env("HOME", temp.path())
+        .env("PATH", &path)
+        .env("WORKTREE_TEST_LOG", &log)
+        .assert()
+        .success();
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["resume", "feat"])
+        .env("HOME", temp.path())
+        .env("PATH", &path)
+        .env("WORKTREE_TEST_LOG", &log)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&log)?,
+        "--dangerously-skip-permissions\n--continue\n"
+    );
+
+    fs::create_dir_all(temp.path().join(".worktree"))?;
+    fs::write(
+        temp.path().join(".worktree/config.toml"),
+        "[commands.claude]\nresume_args = [\"--resume\"]\n",
+    )?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["resume", "feature"])
+        .env("HOME", temp.path())
+        .env("PATH", &path)
+        .env("WORKTREE_TEST_LOG", &log)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&log)?,
+        "--dangerously-skip-permissions\n--resume\n"
+    );
+
+    // Resume rebuilds args from the recorded tool and profile, so config
+    // changes and renames since the launch are picked up.
+    fs::write(
+        temp.path().join(".worktree/config.toml"),
+        "[commands.claude]\nresume_args = [\"--resume\"]\n\
+         [commands.claude.profiles.review]\nargs = [\"--session\", \"{name}\"]\n",
+    )?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["claude", "switch", "--profile", "review", "feature"])
+        .env("HOME", temp.path())
+        .env("PATH", &path)
+        .env("WORKTREE_TEST_LOG", &log)
+        .assert()
+        .success();
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["mv", "feature", "renamed"])
+        .env("HOME", temp.path())
+        .assert()
+        .success();
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["resume", "renamed"])
+        .env("HOME", temp.path())
+        .env("PATH", &path)
+        .env("WORKTREE_TEST_LOG", &log)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&log)?,
+        "--dangerously-skip-permissions\n--session\nrenamed\n--resume\n"
+    );
+
+    let shell = fake_shell(temp.path())?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["create", "plain"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success();
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["resume", "plain"])
+        .env("HOME", temp.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "no tool recorded for worktree 'plain'",
+        ));
+    Ok(())
+}
+
+#[test]
+fn tool_profiles_select_configured_args() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let bin = temp.path().join("bin");
+    fs::create_dir(&bin)?;
+    let log = temp.path().join("run.log");
+    fs::write(
+        bin.join("claude"),
+        r#"#!/bin/sh
+printf "%s\n" "$@" > "$WORKTREE_TEST_LOG"
+"#,
+    )?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(bin.join("claude"), fs::Permissions::from_mode(0o755))?;
+    }
+    let path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_else(|_| String::from("/usr/bin"))
+    );
+    fs::create_dir_all(temp.path().join(".worktree"))?;
+    fs::write(
+        temp.path().join(".worktree/config.toml"),
+        r#"[commands.claude]
+args = ["--verbose"]
+
+[commands.claude.profiles.review]
+args = ["--permission-mode", "plan"]
+replace_defaults = true
+
+[commands.claude.profiles.yolo]
+args = ["--model", "opus"]
+"#,
+    )?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["claude", "create", "--profile", "review", "feature"])
+        .env("HOME", temp.path())
+        .env("PATH", &path)
+        .env("WORKTREE_TEST_LOG", &log)
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&log)?, "--permission-mode\nplan\n");
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["claude", "switch", "--profile", "yolo", "feature"])
+        .env("HOME", temp.path())
+        .env("PATH", &path)
+        .env("WORKTREE_TEST_LOG", &log)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&log)?,
+        "--dangerously-skip-permissions\n--verbose\n--model\nopus\n"
+    );
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["claude", "switch", "--profile", "plan-only", "feature"])
+        .env("HOME", temp.path())
+        .env("PATH", &path)
+        .assert()
+        .code(10)
+        .stderr(predicate::str::contains(
+            "unknown profile 'plan-only' for claude; available: review, yolo",
+        ));
+    Ok(())
+}
+
+#[test]
+fn placeholders_expand_in_configured_args() -> TestResult {
+    let temp = TempDir::new()?;
+    let root = temp.path().join("proj");
+    fs::create_dir(&root)?;
+    init_repo(&root)?;
+    let bin = temp.path().join("bin");
+    fs::create_dir(&bin)?;
+    let log = temp.path().join("run.log");
+    fs::write(
+        bin.join("claude"),
+        r#"#!/bin/sh
+printf "%s\n" "$@" > "$WORKTREE_TEST_LOG"
+"#,
+    )?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(bin.join("claude"), fs::Permissions::from_mode(0o755))?;
+    }
+    let path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_else(|_| String::from("/usr/bin"))
+    );
+    fs::create_dir_all(temp.path().join(".worktree"))?;
+    fs::write(
+        temp.path().join(".worktree/config.toml"),
+        r#"[commands.claude]
+replace_defaults = true
+args = ["--add-dir", "{root}", "--session-name", "{repo}-{name}", "{{literal}}"]
+
+[commands.claude.profiles.broken]
+args = ["{nope}"]
+"#,
+    )?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(&root)
+        .args(["claude", "create", "feature", "--", "{name}"])
+        .env("HOME", temp.path())
+        .env("PATH", &path)
+        .env("WORKTREE_TEST_LOG", &log)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&log)?,
+        format!(
+            "--add-dir\n{}\n--session-name\nproj-feature\n{{literal}}\n{{name}}\n",
+            root.display()
+        )
+    );
+
+    Command::cargo_bin("worktree")?
+        .current_dir(&root)
+        .args(["claude", "switch", "--profile", "broken", "feature"])
+        .env("HOME", temp.path())
+        .env("PATH", &path)
+        .assert()
+        .code(10)
+        .stderr(predicate::str::contains("unknown placeholder '{nope}'"));
+
+    // A new worktree's {branch} and {base} describe what create checks out,
+    // not metadata left behind under the same name.
+    fs::create_dir_all(root.join(".git/worktree-meta"))?;
+    fs::write(
+        root.join(".git/worktree-meta/fresh.toml"),
+        "version = 1\nbase = \"stale\"\n",
+    )?;
+    let head = std::process::Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(&root)
+        .output()?;
+    fs::write(
+        temp.path().join(".worktree/config.toml"),
+        "[commands.claude]\nreplace_defaults = true\nargs = [\"[{branch}]\", \"{base}\"]\n",
+    )?;
+    Command::cargo_bin("worktree")?
+        .current_dir(&root)
+        .args(["claude", "create", "fresh"])
+        .env("HOME", temp.path())
+        .env("PATH", &path)
+        .env("WORKTREE_TEST_LOG", &log)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&log)?,
+        format!("[]\n{}", String::from_utf8(head.stdout)?)
+    );
+    Ok(())
+}
+
+#[test]
+fn history_failure_does_not_block_entering() -> TestResult {
+    let temp = TempDir::new()?;
+    init_repo(temp.path())?;
+    let shell = fake_shell(temp.path())?;
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["create", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success();
+    let history = temp.path().join(".git/worktree-tool.history");
+    fs::remove_file(&history)?;
+    fs::create_dir(&history)?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(temp.path())
+        .args(["switch", "feature"])
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "warning: failed to record history",
+        ));
+    Ok(())
+}
+
+#[test]
+fn shared_worktrees_dir_only_touches_own_checkouts() -> TestResult {
+    let temp = TempDir::new()?;
+    let shell = fake_shell(temp.path())?;
+    fs::create_dir_all(temp.path().join(".worktree"))?;
+    fs::write(
+        temp.path().join(".worktree/config.toml"),
+        "worktrees_dir = \"~/wts/{repo}/{name}\"\n",
+    )?;
+    let (ours, theirs) = (temp.path().join("a/app"), temp.path().join("b/app"));
+    for (repo, name) in [(&ours, "one"), (&theirs, "two")] {
+        fs::create_dir_all(repo)?;
+        init_repo(repo)?;
+        Command::cargo_bin("worktree")?
+            .current_dir(repo)
+            .args(["create", name])
+            .env("HOME", temp.path())
+            .env("SHELL", &shell)
+            .assert()
+            .success();
+    }
+    let shared = temp.path().join("wts/app");
+    fs::write(shared.join("two/wip.txt"), "theirs")?;
+    fs::write(shared.join("notes.txt"), "mine")?;
+
+    Command::cargo_bin("worktree")?
+        .current_dir(&ours)
+        .arg("list")
+        .env("HOME", temp.path())
+        .assert()
+        .success()
+        .stdout("one\n");
+    Command::cargo_bin("worktree")?
+        .current_dir(&ours)
+        .arg("clear")
+        .env("HOME", temp.path())
+        .env("SHELL", &shell)
+        .assert()
+        .success();
+    assert!(!shared.join("one").exists());
+    assert_eq!(fs::read_to_string(shared.join("two/wip.txt"))?, "theirs");
+    assert_eq!(fs::read_to_string(shared.join("notes.txt"))?, "mine");
+
+    fs::write(
+        temp.path().join(".worktree/config.toml"),
+        "worktrees_dir = \"~/wts/{name}/{repo}\"\n",
+    )?;
+    Command::cargo_bin("worktree")?
+        .current_dir(&ours)
+        .arg("list")
+        .env("HOME", temp.path())
+        .assert()
+        .code(10)
+        .stderr(predicate::str::contains(
+            "{name} is only allowed as the last path component",
+        ));
+    Ok(())
+}